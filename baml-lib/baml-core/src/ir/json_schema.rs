@@ -1,5 +1,7 @@
 // JSON Schema
 
+use std::collections::HashMap;
+
 use baml_types::TypeValue;
 use serde_json::json;
 
@@ -19,7 +21,18 @@ impl WithJsonSchema for IntermediateRepr {
             .map(|c| (c.elem().name.clone(), c.json_schema()));
 
         // Combine all the definitions into one object of key-value pairs
-        let definitions = enums.chain(classes).collect::<serde_json::Map<_, _>>();
+        let mut definitions = enums.chain(classes).collect::<serde_json::Map<_, _>>();
+
+        let discriminators = self
+            .walk_classes()
+            .filter_map(|c| {
+                let elem = c.elem();
+                Some((elem.name.as_str(), elem.discriminator.as_deref()?))
+            })
+            .collect::<HashMap<_, _>>();
+        for definition in definitions.values_mut() {
+            tag_unions(definition, &discriminators);
+        }
 
         json!({
             "definitions": definitions,
@@ -76,6 +89,10 @@ impl WithJsonSchema for Walker<'_, &Class> {
                 }
             }
         }
+        if let Some(key) = &self.elem().discriminator {
+            properties[key.clone()] = json!({ "const": self.elem().name });
+            required_props.insert(0, key.clone());
+        }
         json!({
                 "title": self.elem().name,
                 "type": "object",
@@ -85,6 +102,56 @@ impl WithJsonSchema for Walker<'_, &Class> {
     }
 }
 
+/// Rewrites each `anyOf` whose options all reference classes tagged with the same
+/// `@@discriminator` key into a `oneOf` with an OpenAPI style `discriminator`.
+fn tag_unions(schema: &mut serde_json::Value, discriminators: &HashMap<&str, &str>) {
+    match schema {
+        serde_json::Value::Object(obj) => {
+            for value in obj.values_mut() {
+                tag_unions(value, discriminators);
+            }
+
+            let Some(serde_json::Value::Array(options)) = obj.get("anyOf") else {
+                return;
+            };
+            let Some(tagged) = options
+                .iter()
+                .map(|o| {
+                    let name = o["$ref"].as_str()?.strip_prefix("#/definitions/")?;
+                    Some((name, *discriminators.get(name)?))
+                })
+                .collect::<Option<Vec<_>>>()
+            else {
+                return;
+            };
+            let Some((_, key)) = tagged.first().copied() else {
+                return;
+            };
+            if tagged.iter().any(|(_, k)| *k != key) {
+                return;
+            }
+
+            let discriminator = json!({
+                "propertyName": key,
+                "mapping": tagged
+                    .iter()
+                    .map(|(name, _)| (name.to_string(), json!(format!("#/definitions/{}", name))))
+                    .collect::<serde_json::Map<_, _>>(),
+            });
+            if let Some(options) = obj.remove("anyOf") {
+                obj.insert("oneOf".into(), options);
+                obj.insert("discriminator".into(), discriminator);
+            }
+        }
+        serde_json::Value::Array(items) => {
+            for item in items {
+                tag_unions(item, discriminators);
+            }
+        }
+        _ => {}
+    }
+}
+
 impl<'db> WithJsonSchema for FieldType {
     fn json_schema(&self) -> serde_json::Value {
         match self {
@@ -170,3 +237,95 @@ impl<'db> WithJsonSchema for FieldType {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::configuration::Configuration;
+
+    fn json_schema(schema: &str) -> serde_json::Value {
+        let validated = crate::validate(&schema.to_string());
+        assert!(
+            !validated.diagnostics.has_errors(),
+            "{}",
+            validated.diagnostics.to_pretty_string()
+        );
+        IntermediateRepr::from_parser_database(&validated.db, Configuration::new())
+            .unwrap()
+            .json_schema()
+    }
+
+    #[test]
+    fn tagged_unions_use_one_of() {
+        let schema = json_schema(
+            r#"
+            class Cat {
+              lives int
+              @@discriminator("type")
+            }
+
+            class Dog {
+              breed string
+              @@discriminator("type")
+            }
+
+            class Owner {
+              pet Cat | Dog
+            }
+            "#,
+        );
+        let definitions = &schema["definitions"];
+
+        assert_eq!(
+            definitions["Cat"]["properties"]["type"],
+            json!({ "const": "Cat" })
+        );
+        assert_eq!(definitions["Cat"]["required"], json!(["type", "lives"]));
+
+        let pet = &definitions["Owner"]["properties"]["pet"];
+        assert!(pet.get("anyOf").is_none());
+        assert_eq!(
+            pet["oneOf"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|o| o["$ref"].clone())
+                .collect::<Vec<_>>(),
+            [json!("#/definitions/Cat"), json!("#/definitions/Dog")]
+        );
+        assert_eq!(
+            pet["discriminator"],
+            json!({
+                "propertyName": "type",
+                "mapping": {
+                    "Cat": "#/definitions/Cat",
+                    "Dog": "#/definitions/Dog",
+                },
+            })
+        );
+    }
+
+    #[test]
+    fn untagged_unions_keep_any_of() {
+        let schema = json_schema(
+            r#"
+            class Cat {
+              lives int
+              @@discriminator("type")
+            }
+
+            class Dog {
+              breed string
+            }
+
+            class Owner {
+              pet Cat | Dog
+            }
+            "#,
+        );
+        let pet = &schema["definitions"]["Owner"]["properties"]["pet"];
+        assert!(pet.get("oneOf").is_none());
+        assert!(pet.get("discriminator").is_none());
+        assert_eq!(pet["anyOf"].as_array().unwrap().len(), 2);
+    }
+}
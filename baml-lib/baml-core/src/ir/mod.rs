@@ -3,6 +3,8 @@ mod json_schema;
 pub mod repr;
 mod walker;
 
+pub use json_schema::WithJsonSchema;
+
 pub use ir_helpers::{
     ClassFieldWalker, ClassWalker, EnumValueWalker, EnumWalker, IRHelper, TemplateStringWalker,
 };
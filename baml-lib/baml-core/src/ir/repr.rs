@@ -470,6 +470,8 @@ pub struct Class {
     pub name: ClassId,
     pub static_fields: Vec<Node<Field>>,
     pub dynamic_fields: Vec<Node<Field>>,
+    /// The key that tags this class with its name in unions (`@@discriminator`).
+    pub discriminator: Option<String>,
 }
 
 impl WithRepr<Class> for ClassWalker<'_> {
@@ -495,6 +497,7 @@ impl WithRepr<Class> for ClassWalker<'_> {
                 .dynamic_fields()
                 .map(|e| e.node(db))
                 .collect::<Result<Vec<_>>>()?,
+            discriminator: self.discriminator().map(String::from),
         })
     }
 }
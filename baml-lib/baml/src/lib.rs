@@ -85,6 +85,7 @@ impl BamlContext {
             None,
             Some(Some(String::new())),
             Some(true),
+            None,
         ))?;
 
         Ok(output.unwrap_or_default())
@@ -92,9 +93,27 @@ impl BamlContext {
 
     /// Check the LLM output for validity.
     pub fn validate_result(&self, result: &String) -> anyhow::Result<String> {
-        let result = jsonish::from_str(&self.format, &self.target, &result, false);
+        self.validate_result_with_options(result, &jsonish::DeserializerOptions::default())
+    }
+
+    /// Same as [`Self::validate_result`], with control over how the output is parsed.
+    /// Set `omit_missing_optional_fields` to leave fields the LLM never mentioned
+    /// out of the returned JSON, rather than rendering them as `null`, and
+    /// `enum_fallback_raw` to return enums set to their `@@fallback` value as
+    /// `{"value": ..., "raw": ...}`.
+    pub fn validate_result_with_options(
+        &self,
+        result: &String,
+        options: &jsonish::DeserializerOptions,
+    ) -> anyhow::Result<String> {
+        let result =
+            jsonish::from_str_with_options(&self.format, &self.target, &result, false, options);
         result.map(|r| {
-            let baml_value: BamlValue = r.into();
+            let baml_value: BamlValue = if options.enum_fallback_raw {
+                r.into_value_with_fallback_raw()
+            } else {
+                r.into()
+            };
             // BAML serializes values using `serde_json::json!` which adds quotes around strings.
             // Enum result is a JSON string, so remove quotes around it.
             baml_value
@@ -160,6 +179,12 @@ impl BamlContext {
                 internal_baml_jinja::Enum {
                     name: ast_enum.name.name().to_string().into(),
                     values,
+                    fallback: e.fallback().map(|v| v.name().to_string()),
+                    synonyms: e
+                        .values()
+                        .filter(|v| !v.synonyms().is_empty())
+                        .map(|v| (v.name().to_string(), v.synonyms().to_vec()))
+                        .collect(),
                 }
             })
             .collect::<Vec<_>>();
@@ -187,6 +212,8 @@ impl BamlContext {
                 internal_baml_jinja::Class {
                     name: ast_class.name.name().to_string().into(),
                     fields,
+                    extra_fields: c.extra_fields().map(|f| f.name().to_string()),
+                    discriminator: c.discriminator().map(String::from),
                 }
             })
             .collect::<Vec<_>>();
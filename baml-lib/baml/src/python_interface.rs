@@ -1,4 +1,8 @@
-use pyo3::{create_exception, PyErr};
+use pyo3::{
+    create_exception,
+    types::{PyAnyMethods, PyDict, PyList},
+    IntoPy, PyErr, PyObject, Python,
+};
 
 use crate::BamlContext;
 
@@ -6,7 +10,48 @@ create_exception!(lmnr_baml, LaminarBamlError, pyo3::exceptions::PyException);
 
 impl LaminarBamlError {
     fn from_anyhow(err: anyhow::Error) -> PyErr {
-        PyErr::new::<LaminarBamlError, _>(format!("{:?}", err))
+        let py_err = PyErr::new::<LaminarBamlError, _>(format!("{:?}", err));
+        // When the output didn't coerce, say where and why as attributes: `kind`,
+        // `path` (a JSON pointer), `raw` and `causes`, the same errors as dicts.
+        if let Some(coercion) = err.downcast_ref::<jsonish::CoercionError>() {
+            if let Ok(serde_json::Value::Object(error)) =
+                serde_json::to_value(coercion.parsing_error())
+            {
+                Python::with_gil(|py| {
+                    let value = py_err.value_bound(py);
+                    for attr in ["kind", "path", "raw", "causes"] {
+                        let attr_value = error
+                            .get(attr)
+                            .map_or_else(|| py.None(), |v| json_to_py(py, v));
+                        // Setting an attribute on a fresh exception can't fail.
+                        let _ = value.setattr(attr, attr_value);
+                    }
+                });
+            }
+        }
+        py_err
+    }
+}
+
+fn json_to_py(py: Python<'_>, value: &serde_json::Value) -> PyObject {
+    match value {
+        serde_json::Value::Null => py.None(),
+        serde_json::Value::Bool(b) => b.into_py(py),
+        serde_json::Value::Number(n) => match n.as_i64() {
+            Some(i) => i.into_py(py),
+            None => n.as_f64().unwrap_or_default().into_py(py),
+        },
+        serde_json::Value::String(s) => s.into_py(py),
+        serde_json::Value::Array(items) => {
+            PyList::new_bound(py, items.iter().map(|v| json_to_py(py, v))).into_py(py)
+        }
+        serde_json::Value::Object(map) => {
+            let dict = PyDict::new_bound(py);
+            for (k, v) in map {
+                let _ = dict.set_item(k, json_to_py(py, v));
+            }
+            dict.into_py(py)
+        }
     }
 }
 
@@ -24,15 +69,28 @@ pub fn render_prompt(
 }
 
 #[pyo3::pyfunction]
-#[pyo3(signature = (schema_string, result, target_name=None))]
+#[pyo3(signature = (
+    schema_string,
+    result,
+    target_name=None,
+    omit_missing_fields=false,
+    enum_fallback_raw=false
+))]
 pub fn validate_result(
     schema_string: String,
     result: String,
     target_name: Option<String>,
+    omit_missing_fields: bool,
+    enum_fallback_raw: bool,
 ) -> pyo3::prelude::PyResult<String> {
     let baml_context = BamlContext::try_from_schema(&schema_string, target_name)
         .map_err(LaminarBamlError::from_anyhow)?;
+    let options = jsonish::DeserializerOptions {
+        omit_missing_optional_fields: omit_missing_fields,
+        enum_fallback_raw,
+        ..Default::default()
+    };
     baml_context
-        .validate_result(&result)
+        .validate_result_with_options(&result, &options)
         .map_err(LaminarBamlError::from_anyhow)
 }
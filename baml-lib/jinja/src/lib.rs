@@ -806,6 +806,137 @@ mod render_tests {
         Ok(())
     }
 
+    #[test]
+    fn render_output_format_numbered_enum() -> anyhow::Result<()> {
+        setup_logging();
+
+        let output_format = OutputFormatContent::new(
+            vec![types::Enum {
+                name: types::Name::new("Category".to_string()),
+                values: vec![
+                    (types::Name::new("Billing".to_string()), None),
+                    (
+                        types::Name::new("Shipping".to_string()),
+                        Some("Delivery issues".to_string()),
+                    ),
+                ],
+                fallback: None,
+                synonyms: Default::default(),
+            }],
+            vec![],
+            baml_types::FieldType::Enum("Category".to_string()),
+        );
+
+        let rendered = render_prompt(
+            "{{ ctx.output_format(prefix=null, number_enum_values=true) }}",
+            &BamlValue::Map(BamlMap::new()),
+            RenderContext {
+                client: RenderContext_Client {
+                    name: "gpt4".to_string(),
+                    provider: "openai".to_string(),
+                },
+                output_format,
+                tags: HashMap::new(),
+            },
+            &[],
+        )?;
+
+        assert_eq!(
+            rendered,
+            RenderedPrompt::Completion(
+                "enum Category\n----\n1. Billing\n2. Shipping: Delivery issues".to_string()
+            )
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn render_output_format_hides_extra_fields() -> anyhow::Result<()> {
+        setup_logging();
+
+        let string = baml_types::FieldType::Primitive(baml_types::TypeValue::String);
+        let output_format = OutputFormatContent::new(
+            vec![],
+            vec![types::Class {
+                name: types::Name::new("Person".to_string()),
+                fields: vec![
+                    (types::Name::new("name".to_string()), string.clone(), None),
+                    (
+                        types::Name::new("other".to_string()),
+                        baml_types::FieldType::Map(Box::new(string.clone()), Box::new(string)),
+                        None,
+                    ),
+                ],
+                extra_fields: Some("other".to_string()),
+                discriminator: None,
+            }],
+            baml_types::FieldType::Class("Person".to_string()),
+        );
+
+        let rendered = render_prompt(
+            "{{ ctx.output_format(prefix=null) }}",
+            &BamlValue::Map(BamlMap::new()),
+            RenderContext {
+                client: RenderContext_Client {
+                    name: "gpt4".to_string(),
+                    provider: "openai".to_string(),
+                },
+                output_format,
+                tags: HashMap::new(),
+            },
+            &[],
+        )?;
+
+        assert_eq!(
+            rendered,
+            RenderedPrompt::Completion("{\n  name: string,\n}".to_string())
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn render_output_format_discriminator() -> anyhow::Result<()> {
+        setup_logging();
+
+        let output_format = OutputFormatContent::new(
+            vec![],
+            vec![types::Class {
+                name: types::Name::new_with_alias("Cat".to_string(), Some("cat".to_string())),
+                fields: vec![(
+                    types::Name::new("name".to_string()),
+                    baml_types::FieldType::Primitive(baml_types::TypeValue::String),
+                    None,
+                )],
+                extra_fields: None,
+                discriminator: Some("type".to_string()),
+            }],
+            baml_types::FieldType::Class("Cat".to_string()),
+        );
+
+        let rendered = render_prompt(
+            "{{ ctx.output_format(prefix=null) }}",
+            &BamlValue::Map(BamlMap::new()),
+            RenderContext {
+                client: RenderContext_Client {
+                    name: "gpt4".to_string(),
+                    provider: "openai".to_string(),
+                },
+                output_format,
+                tags: HashMap::new(),
+            },
+            &[],
+        )?;
+
+        assert_eq!(
+            rendered,
+            RenderedPrompt::Completion("{\n  type: \"cat\",\n  name: string,\n}".to_string())
+        );
+
+        Ok(())
+    }
+
     #[test]
     fn render_chat_param_failures() -> anyhow::Result<()> {
         setup_logging();
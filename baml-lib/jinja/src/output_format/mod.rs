@@ -111,10 +111,27 @@ impl minijinja::value::Object for OutputFormat {
             None
         };
 
+        let number_enum_values = if kwargs.has("number_enum_values") {
+            match kwargs.get::<bool>("number_enum_values") {
+                Ok(number_enum_values) => Some(number_enum_values),
+                Err(e) => {
+                    return Err(Error::new(
+                        ErrorKind::SyntaxError,
+                        format!(
+                            "Invalid value for number_enum_values (expected bool): {}",
+                            e
+                        ),
+                    ))
+                }
+            }
+        } else {
+            None
+        };
+
         let Ok(_) = kwargs.assert_all_used() else {
             return Err(Error::new(
                 ErrorKind::TooManyArguments,
-                "output_format() got an unexpected keyword argument (only 'prefix', 'always_host_enums', 'enum_value_prefix', and 'or_splitter' are allowed)",
+                "output_format() got an unexpected keyword argument (only 'prefix', 'always_host_enums', 'enum_value_prefix', 'number_enum_values', and 'or_splitter' are allowed)",
             ));
         };
 
@@ -123,6 +140,7 @@ impl minijinja::value::Object for OutputFormat {
             or_splitter,
             enum_value_prefix,
             always_hoist_enums,
+            number_enum_values,
         ))?;
 
         match content {
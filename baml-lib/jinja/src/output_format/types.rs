@@ -48,6 +48,10 @@ pub struct Enum {
     pub name: Name,
     // name and description
     pub values: Vec<(Name, Option<String>)>,
+    // real name of the value used when nothing else matches
+    pub fallback: Option<String>,
+    // real name and extra strings it matches, not rendered
+    pub synonyms: IndexMap<String, Vec<String>>,
 }
 
 #[derive(Debug)]
@@ -55,6 +59,10 @@ pub struct Class {
     pub name: Name,
     // type and description
     pub fields: Vec<(Name, FieldType, Option<String>)>,
+    // real name of the field that collects unknown keys, not rendered
+    pub extra_fields: Option<String>,
+    // key that tags the class with its name in unions, rendered as the first field
+    pub discriminator: Option<String>,
 }
 
 #[derive(Debug, Clone)]
@@ -83,6 +91,9 @@ pub struct RenderOptions {
     pub or_splitter: String,
     pub enum_value_prefix: RenderSetting<String>,
     pub always_hoist_enums: RenderSetting<bool>,
+    /// Render enum values as a numbered list (`1. A`, `2. B`) instead of with
+    /// `enum_value_prefix`, so models can answer with an ordinal.
+    pub number_enum_values: RenderSetting<bool>,
 }
 
 impl Default for RenderOptions {
@@ -92,6 +103,7 @@ impl Default for RenderOptions {
             or_splitter: " or ".to_string(),
             enum_value_prefix: RenderSetting::Auto,
             always_hoist_enums: RenderSetting::Auto,
+            number_enum_values: RenderSetting::Auto,
         }
     }
 }
@@ -102,6 +114,7 @@ impl RenderOptions {
         or_splitter: Option<String>,
         enum_value_prefix: Option<Option<String>>,
         always_hoist_enums: Option<bool>,
+        number_enum_values: Option<bool>,
     ) -> Self {
         Self {
             prefix: prefix.map_or(RenderSetting::Auto, |p| {
@@ -113,6 +126,8 @@ impl RenderOptions {
             }),
             always_hoist_enums: always_hoist_enums
                 .map_or(RenderSetting::Auto, RenderSetting::Always),
+            number_enum_values: number_enum_values
+                .map_or(RenderSetting::Auto, RenderSetting::Always),
         }
     }
 }
@@ -131,16 +146,18 @@ struct EnumRender {
 impl EnumRender {
     fn to_string(&self, options: &RenderOptions) -> String {
         let mut result = format!("enum {}\n{}", self.name, self.delimiter);
-        for value in &self.values {
-            result.push_str(&format!(
-                "\n{}{}",
+        let numbered = matches!(options.number_enum_values, RenderSetting::Always(true));
+        for (idx, value) in self.values.iter().enumerate() {
+            let prefix = if numbered {
+                format!("{}. ", idx + 1)
+            } else {
                 match options.enum_value_prefix {
-                    RenderSetting::Auto => "- ",
-                    RenderSetting::Always(ref prefix) => prefix,
-                    RenderSetting::Never => "",
-                },
-                value.to_string()
-            ));
+                    RenderSetting::Auto => "- ".into(),
+                    RenderSetting::Always(ref prefix) => prefix.clone(),
+                    RenderSetting::Never => "".into(),
+                }
+            };
+            result.push_str(&format!("\n{}{}", prefix, value.to_string()));
         }
         result
     }
@@ -298,18 +315,38 @@ impl OutputFormatContent {
                     ));
                 };
 
+                let tag = class.discriminator.as_ref().map(|key| {
+                    Ok(ClassFieldRender {
+                        name: key.clone(),
+                        r#type: format!("\"{}\"", class.name.rendered_name()),
+                        description: None,
+                    })
+                });
+
                 ClassRender {
                     name: class.name.rendered_name().to_string(),
-                    values: class
-                        .fields
-                        .iter()
-                        .map(|(n, t, d)| {
-                            Ok(ClassFieldRender {
-                                name: n.rendered_name().to_string(),
-                                r#type: self.inner_type_render(options, t, render_state, false)?,
-                                description: d.clone(),
-                            })
-                        })
+                    values: tag
+                        .into_iter()
+                        .chain(
+                            class
+                                .fields
+                                .iter()
+                                .filter(|(n, ..)| {
+                                    class.extra_fields.as_deref() != Some(n.real_name())
+                                })
+                                .map(|(n, t, d)| {
+                                    Ok(ClassFieldRender {
+                                        name: n.rendered_name().to_string(),
+                                        r#type: self.inner_type_render(
+                                            options,
+                                            t,
+                                            render_state,
+                                            false,
+                                        )?,
+                                        description: d.clone(),
+                                    })
+                                }),
+                        )
                         .collect::<Result<_, minijinja::Error>>()?,
                 }
                 .to_string()
@@ -464,4 +501,8 @@ impl OutputFormatContent {
             .get(name)
             .ok_or_else(|| anyhow::anyhow!("Class {} not found", name))
     }
+
+    pub fn target(&self) -> &FieldType {
+        &self.target
+    }
 }
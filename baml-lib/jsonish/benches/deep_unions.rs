@@ -0,0 +1,89 @@
+//! Parses a nested object against a schema where every level is a union of
+//! two classes, with and without `DeserializerOptions::memoize`.
+//!
+//! Run with `cargo bench -p jsonish --bench deep_unions`.
+
+use std::time::{Duration, Instant};
+
+use baml_types::{FieldType, TypeValue};
+use internal_baml_jinja::types::{Class, Name, OutputFormatContent};
+use jsonish::DeserializerOptions;
+
+const RUNS: u32 = 3;
+
+fn class(name: String, key: &str, next: Option<FieldType>) -> Class {
+    let string = FieldType::Primitive(TypeValue::String);
+    let mut fields = vec![(Name::new(key.into()), string, None)];
+    if let Some(next) = next {
+        fields.push((
+            Name::new("next".into()),
+            FieldType::Optional(Box::new(next)),
+            None,
+        ));
+    }
+    Class {
+        name: Name::new(name),
+        fields,
+        extra_fields: None,
+        discriminator: None,
+    }
+}
+
+/// Levels `0..depth`, each `L{d}a | L{d}b` with a `next` field pointing at the
+/// union of the level below. Only the `a` classes match the input, but both
+/// options of every union still coerce `next`.
+fn schema(depth: usize) -> (OutputFormatContent, FieldType) {
+    let level = |d: usize| {
+        FieldType::Union(vec![
+            FieldType::Class(format!("L{d}a")),
+            FieldType::Class(format!("L{d}b")),
+        ])
+    };
+    let classes = (0..depth)
+        .flat_map(|d| {
+            let next = (d + 1 < depth).then(|| level(d + 1));
+            [
+                class(format!("L{d}a"), "a", next.clone()),
+                class(format!("L{d}b"), "b", next),
+            ]
+        })
+        .collect();
+    let target = level(0);
+    (
+        OutputFormatContent::new(vec![], classes, target.clone()),
+        target,
+    )
+}
+
+/// The object is wrapped in prose, so every level is reached both through the
+/// fenced block and the raw string candidates.
+fn input(depth: usize) -> String {
+    let mut json = String::from("{\"a\": \"leaf\"}");
+    for d in (0..depth - 1).rev() {
+        json = format!("{{\"a\": \"level {d}\", \"next\": {json}}}");
+    }
+    format!("Here you go:\n```json\n{json}\n```\nand again: {json}")
+}
+
+fn time(of: &OutputFormatContent, target: &FieldType, raw: &str, memoize: bool) -> Duration {
+    let options = DeserializerOptions {
+        memoize,
+        ..Default::default()
+    };
+    let start = Instant::now();
+    for _ in 0..RUNS {
+        jsonish::from_str_with_options(of, target, raw, false, &options).unwrap();
+    }
+    start.elapsed() / RUNS
+}
+
+fn main() {
+    println!("{:>5} {:>14} {:>14}", "depth", "memoized", "unmemoized");
+    for depth in [4, 8, 12, 14] {
+        let (of, target) = schema(depth);
+        let raw = input(depth);
+        let memoized = time(&of, &target, &raw, true);
+        let unmemoized = time(&of, &target, &raw, false);
+        println!("{depth:>5} {memoized:>14.2?} {unmemoized:>14.2?}");
+    }
+}
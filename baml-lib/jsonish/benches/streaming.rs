@@ -0,0 +1,101 @@
+//! Streams a response made of a list of objects in small chunks, getting the
+//! value after every chunk like a caller rendering partial results would. The
+//! same prefixes are also parsed from scratch with `from_str` for comparison.
+//!
+//! Run with `cargo bench -p jsonish --bench streaming`.
+
+use std::time::{Duration, Instant};
+
+use baml_types::{FieldType, TypeValue};
+use internal_baml_jinja::types::{Class, Name, OutputFormatContent};
+use jsonish::{DeserializerOptions, StreamingParser};
+
+const CHUNK: usize = 16;
+
+fn schema() -> (OutputFormatContent, FieldType) {
+    let string = FieldType::Primitive(TypeValue::String);
+    let item = Class {
+        name: Name::new("Item".into()),
+        fields: vec![
+            (
+                Name::new("id".into()),
+                FieldType::Primitive(TypeValue::Int),
+                None,
+            ),
+            (Name::new("name".into()), string.clone(), None),
+        ],
+        extra_fields: None,
+        discriminator: None,
+    };
+    let list = Class {
+        name: Name::new("List".into()),
+        fields: vec![(
+            Name::new("items".into()),
+            FieldType::List(Box::new(FieldType::Class("Item".into()))),
+            None,
+        )],
+        extra_fields: None,
+        discriminator: None,
+    };
+    let target = FieldType::Class("List".into());
+    (
+        OutputFormatContent::new(vec![], vec![item, list], target.clone()),
+        target,
+    )
+}
+
+fn input(items: usize) -> String {
+    let items = (0..items)
+        .map(|i| format!("{{\"id\": {i}, \"name\": \"item {i}\"}}"))
+        .collect::<Vec<_>>()
+        .join(", ");
+    format!("Sure, here they are:\n{{\"items\": [{items}]}}\nLet me know if you need more.")
+}
+
+fn chunks(raw: &str) -> impl Iterator<Item = &str> {
+    (0..raw.len())
+        .step_by(CHUNK)
+        .map(move |start| &raw[start..(start + CHUNK).min(raw.len())])
+}
+
+fn time_stream(of: &OutputFormatContent, target: &FieldType, raw: &str, poll: bool) -> Duration {
+    let options = DeserializerOptions::default();
+    let start = Instant::now();
+    let mut stream = StreamingParser::default();
+    for chunk in chunks(raw) {
+        stream.push(chunk);
+        if poll {
+            let _ = jsonish::from_stream(of, target, &stream, true, &options);
+        }
+    }
+    start.elapsed()
+}
+
+fn time_from_str(of: &OutputFormatContent, target: &FieldType, raw: &str) -> Duration {
+    let options = DeserializerOptions::default();
+    let start = Instant::now();
+    let mut end = 0;
+    for chunk in chunks(raw) {
+        end += chunk.len();
+        let _ = jsonish::from_str_with_options(of, target, &raw[..end], true, &options);
+    }
+    start.elapsed()
+}
+
+fn main() {
+    println!(
+        "{:>6} {:>8} {:>14} {:>14} {:>14}",
+        "items", "chunks", "push", "push + value", "from_str"
+    );
+    let (of, target) = schema();
+    for items in [50, 100, 200, 400] {
+        let raw = input(items);
+        let push = time_stream(&of, &target, &raw, false);
+        let poll = time_stream(&of, &target, &raw, true);
+        let from_str = time_from_str(&of, &target, &raw);
+        println!(
+            "{items:>6} {:>8} {push:>14.2?} {poll:>14.2?} {from_str:>14.2?}",
+            chunks(&raw).count()
+        );
+    }
+}
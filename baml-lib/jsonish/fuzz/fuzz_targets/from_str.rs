@@ -0,0 +1,11 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|raw: &str| {
+    let (of, targets) = jsonish::fuzzing::schema();
+    for target in &targets {
+        let _ = jsonish::from_str(&of, target, raw, true);
+        let _ = jsonish::from_str(&of, target, raw, false);
+    }
+});
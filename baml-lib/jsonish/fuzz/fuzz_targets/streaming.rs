@@ -0,0 +1,16 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|input: (u8, &str)| {
+    let (chunk_chars, raw) = input;
+    let (of, targets) = jsonish::fuzzing::schema();
+    let options = Default::default();
+
+    let mut stream = jsonish::StreamingParser::default();
+    let chars = raw.chars().collect::<Vec<_>>();
+    for chunk in chars.chunks(1 + chunk_chars as usize % 16) {
+        stream.push(&chunk.iter().collect::<String>());
+        let _ = jsonish::from_stream(&of, &targets[0], &stream, true, &options);
+    }
+});
@@ -0,0 +1,311 @@
+use baml_types::BamlValue;
+
+use super::{deserialize_flags::Flag, score::ScoringPolicy, types::BamlValueWithFlags};
+
+/// Ways to read a response that coerce to different values, best first. See
+/// [`crate::alternatives_from_str`].
+#[derive(Debug, Clone)]
+pub struct Alternatives {
+    pub ranked: Vec<Alternative>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Alternative {
+    pub value: BamlValueWithFlags,
+    /// Lower is better.
+    pub score: i32,
+    /// Flags, by name and the path of the value they are on, that set this
+    /// alternative apart: those the best one doesn't have, or for the best
+    /// one, those the runner-up doesn't have.
+    pub distinct_flags: Vec<(Vec<String>, &'static str)>,
+}
+
+impl Alternatives {
+    /// How far the runner-up scored behind the best alternative. `Some(0)` is
+    /// a tie, and `None` means nothing else came close enough to be picked
+    /// over.
+    pub fn ambiguity(&self) -> Option<i32> {
+        match self.ranked.as_slice() {
+            [best, runner_up, ..] => Some(runner_up.score - best.score),
+            _ => None,
+        }
+    }
+}
+
+/// Ranks `picked` against the values it was picked over, and against
+/// `picked` with one of the picks nested in it made differently. Candidates
+/// are only kept in `Flag::FirstMatch` with `DeserializerOptions::keep_alternatives`.
+pub(crate) fn rank(picked: BamlValueWithFlags, k: usize, policy: &ScoringPolicy) -> Alternatives {
+    let mut candidates = others(&picked);
+    candidates.insert(0, picked);
+
+    let mut scored = candidates
+        .into_iter()
+        .map(|v| (v.score_with(policy), v))
+        .collect::<Vec<_>>();
+    // Stable, so ties keep the order they would have been picked in.
+    scored.sort_by_key(|(score, _)| *score);
+
+    let mut seen: Vec<BamlValue> = vec![];
+    let mut ranked = vec![];
+    for (score, value) in scored {
+        if ranked.len() == k {
+            break;
+        }
+        let plain = BamlValue::from(&value);
+        if seen.contains(&plain) {
+            continue;
+        }
+        seen.push(plain);
+        ranked.push((score, value));
+    }
+
+    let flags = ranked
+        .iter()
+        .map(|(_, v)| flag_names(v))
+        .collect::<Vec<_>>();
+    let ranked = ranked
+        .into_iter()
+        .enumerate()
+        .map(|(i, (score, value))| {
+            let against = if i == 0 { flags.get(1) } else { flags.first() };
+            let distinct_flags = flags[i]
+                .iter()
+                .filter(|f| against.is_none_or(|other| !other.contains(f)))
+                .cloned()
+                .collect();
+            Alternative {
+                value,
+                score,
+                distinct_flags,
+            }
+        })
+        .collect();
+    Alternatives { ranked }
+}
+
+/// The values that differ from `value` in one pick: what its outermost pick
+/// was made over, or what the picks nested in the picked value were.
+fn others(value: &BamlValueWithFlags) -> Vec<BamlValueWithFlags> {
+    let mut out = vec![];
+    if let Some((picked, candidates)) = picked_over(value) {
+        for (i, candidate) in candidates.into_iter().enumerate() {
+            match candidate {
+                Some(candidate) if i == picked => out.extend(others(&candidate)),
+                Some(candidate) => expand(candidate, &mut out),
+                None => {}
+            }
+        }
+        return out;
+    }
+
+    // Each nested pick made differently in an otherwise unchanged copy.
+    match value {
+        BamlValueWithFlags::List(_, items) => {
+            for (i, item) in items.iter().enumerate() {
+                for other in others(item) {
+                    let mut value = value.clone();
+                    if let BamlValueWithFlags::List(_, items) = &mut value {
+                        items[i] = other;
+                    }
+                    out.push(value);
+                }
+            }
+        }
+        BamlValueWithFlags::Map(_, kv) => {
+            for (key, (_, item)) in kv {
+                for other in others(item) {
+                    let mut value = value.clone();
+                    if let BamlValueWithFlags::Map(_, kv) = &mut value {
+                        kv.insert(key.clone(), (kv[key].0.clone(), other));
+                    }
+                    out.push(value);
+                }
+            }
+        }
+        BamlValueWithFlags::Class(_, _, kv) => {
+            for (key, item) in kv {
+                for other in others(item) {
+                    let mut value = value.clone();
+                    if let BamlValueWithFlags::Class(_, _, kv) = &mut value {
+                        kv.insert(key.clone(), other);
+                    }
+                    out.push(value);
+                }
+            }
+        }
+        _ => {}
+    }
+    out
+}
+
+/// Pushes `value`, or if it was picked over others, each of them in its place.
+fn expand(value: BamlValueWithFlags, out: &mut Vec<BamlValueWithFlags>) {
+    match picked_over(&value) {
+        Some((_, candidates)) => candidates
+            .into_iter()
+            .flatten()
+            .for_each(|c| expand(c, out)),
+        None => out.push(value),
+    }
+}
+
+/// For the outermost pick made for `value`, the index of the picked candidate
+/// and every candidate that coerced, as `value` would be had it been picked.
+fn picked_over(value: &BamlValueWithFlags) -> Option<(usize, Vec<Option<BamlValueWithFlags>>)> {
+    let conditions = value.conditions();
+    // Picks made further out come later, so this is the outermost one.
+    let pos = conditions
+        .flags
+        .iter()
+        .rposition(|f| matches!(f, Flag::FirstMatch(_, Some(_))))?;
+    let Flag::FirstMatch(picked, Some(candidates)) = &conditions.flags[pos] else {
+        unreachable!()
+    };
+
+    // Whatever was added after the pick applies to every candidate, and each
+    // is scored as picked, like `value` is.
+    let added = &conditions.flags[pos + 1..];
+    let candidates = candidates
+        .iter()
+        .enumerate()
+        .map(|(i, candidate)| {
+            let mut candidate = candidate.as_ref().ok()?.clone();
+            candidate.add_flag(Flag::FirstMatch(i, None));
+            for flag in added {
+                candidate.add_flag(flag.clone());
+            }
+            if let Some(span) = &conditions.span {
+                candidate
+                    .conditions_mut()
+                    .set_span(span, &conditions.key_span);
+            }
+            Some(candidate)
+        })
+        .collect();
+    Some((*picked, candidates))
+}
+
+/// Every flag but `FirstMatch` in `value`, with the path of the value it is on.
+fn flag_names(value: &BamlValueWithFlags) -> Vec<(Vec<String>, &'static str)> {
+    let mut flags = vec![];
+    value.walk(&mut vec![], &mut |path, value| {
+        for flag in &value.conditions().flags {
+            if !matches!(flag, Flag::FirstMatch(..))
+                && !flags.contains(&(path.to_vec(), flag.name()))
+            {
+                flags.push((path.to_vec(), flag.name()));
+            }
+        }
+    });
+    flags
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::DeserializerOptions;
+    use baml_types::{FieldType, TypeValue};
+    use internal_baml_jinja::types::{Class, Name, OutputFormatContent};
+    use test_log::test;
+
+    fn alternatives(target: &FieldType, raw: &str, k: usize) -> anyhow::Result<Alternatives> {
+        let person = Class {
+            name: Name::new("Person".into()),
+            fields: vec![(
+                Name::new("name".into()),
+                FieldType::Primitive(TypeValue::String),
+                None,
+            )],
+            extra_fields: None,
+            discriminator: None,
+        };
+        let point = Class {
+            name: Name::new("Point".into()),
+            fields: vec![(
+                Name::new("x".into()),
+                FieldType::Union(vec![
+                    FieldType::Primitive(TypeValue::Int),
+                    FieldType::Primitive(TypeValue::String),
+                ]),
+                None,
+            )],
+            extra_fields: None,
+            discriminator: None,
+        };
+        let of = OutputFormatContent::new(vec![], vec![person, point], target.clone());
+        crate::alternatives_from_str(&of, target, raw, false, &DeserializerOptions::default(), k)
+    }
+
+    fn person(name: &str) -> BamlValue {
+        BamlValue::Class(
+            "Person".into(),
+            [("name".to_string(), BamlValue::String(name.into()))]
+                .into_iter()
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn objects_in_prose_tie() -> anyhow::Result<()> {
+        let target = FieldType::Class("Person".into());
+        let raw = r#"Either {"name": "Ann"} or {"name": "Bob"}"#;
+
+        let res = alternatives(&target, raw, 2)?;
+        let values = res
+            .ranked
+            .iter()
+            .map(|a| BamlValue::from(&a.value))
+            .collect::<Vec<_>>();
+        assert_eq!(values, vec![person("Ann"), person("Bob")]);
+        assert_eq!(res.ambiguity(), Some(0));
+
+        assert!(alternatives(&target, raw, 10)?.ranked.len() > 2);
+        Ok(())
+    }
+
+    #[test]
+    fn union_options_differ_by_flags() -> anyhow::Result<()> {
+        let string = FieldType::Primitive(TypeValue::String);
+        let target = FieldType::Union(vec![FieldType::Class("Person".into()), string]);
+        let res = alternatives(&target, r#"{"name": "Ann"}"#, 3)?;
+
+        assert_eq!(res.ranked.len(), 2);
+        assert_eq!(BamlValue::from(&res.ranked[0].value), person("Ann"));
+        assert!(res.ranked[0].distinct_flags.is_empty());
+        assert!(matches!(
+            BamlValue::from(&res.ranked[1].value),
+            BamlValue::String(_)
+        ));
+        assert_eq!(res.ranked[1].distinct_flags, vec![(vec![], "JsonToString")]);
+        assert_eq!(res.ambiguity(), Some(2));
+
+        let int = FieldType::Primitive(TypeValue::Int);
+        assert_eq!(alternatives(&int, "3", 3)?.ambiguity(), None);
+        Ok(())
+    }
+
+    #[test]
+    fn nested_picks_are_ranked() -> anyhow::Result<()> {
+        let target = FieldType::Class("Point".into());
+        let res = alternatives(&target, r#"{"x": "5"}"#, 3)?;
+        let point =
+            |x| BamlValue::Class("Point".into(), [("x".to_string(), x)].into_iter().collect());
+
+        let values = res
+            .ranked
+            .iter()
+            .map(|a| BamlValue::from(&a.value))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            values,
+            vec![
+                point(BamlValue::Int(5)),
+                point(BamlValue::String("5".into()))
+            ]
+        );
+        // "5" is as good an int as it is a string, the union's order decides.
+        assert_eq!(res.ambiguity(), Some(0));
+        Ok(())
+    }
+}
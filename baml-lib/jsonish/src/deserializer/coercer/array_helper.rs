@@ -2,7 +2,7 @@ use crate::deserializer::{deserialize_flags::Flag, types::BamlValueWithFlags};
 use anyhow::Result;
 use internal_baml_core::ir::FieldType;
 
-use super::{ParsingContext, ParsingError};
+use super::{field_type::coerce_spanned, ParsingContext, ParsingError};
 
 pub fn coerce_array_to_singular(
     ctx: &ParsingContext,
@@ -10,7 +10,10 @@ pub fn coerce_array_to_singular(
     items: &[&crate::jsonish::Value],
     coercion: &dyn (Fn(&crate::jsonish::Value) -> Result<BamlValueWithFlags, ParsingError>),
 ) -> Result<BamlValueWithFlags, ParsingError> {
-    let parsed = items.iter().map(|item| coercion(item)).collect::<Vec<_>>();
+    let parsed = items
+        .iter()
+        .map(|item| coerce_spanned(item, coercion))
+        .collect::<Vec<_>>();
     match pick_best(ctx, target, &parsed) {
         Ok(v) => Ok(v),
         Err(e) => Err(e),
@@ -32,7 +35,7 @@ pub(super) fn pick_best(
 
     let mut res_index = (0..res.len())
         .map(|i| match res[i] {
-            Ok(ref v) => (i, v.score()),
+            Ok(ref v) => (i, v.score_with(&ctx.options.scoring)),
             Err(_) => (i, i32::max_value()),
         })
         .collect::<Vec<_>>();
@@ -67,7 +70,8 @@ pub(super) fn pick_best(
                 // Add some flags so we know which value we picked
                 let mut v = v.clone();
                 if res.len() > 1 {
-                    v.add_flag(Flag::FirstMatch(i, res.to_vec()));
+                    let alternatives = ctx.options.keep_alternatives.then(|| res.into());
+                    v.add_flag(Flag::FirstMatch(i, alternatives));
                 }
                 Ok(v.to_owned())
             }
@@ -77,10 +81,7 @@ pub(super) fn pick_best(
                     Ok(_) => None,
                     Err(e) => Some(e),
                 });
-                Err(ctx.error_merge_multiple(
-                    &format!("Failed to find any {} in {} items", target, res.len()),
-                    errors,
-                ))
+                Err(ctx.error_no_candidate(target, errors))
             }
             None => Err(ctx.error_internal("Index out of bounds")),
         },
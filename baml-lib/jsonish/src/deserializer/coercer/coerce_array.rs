@@ -6,7 +6,7 @@ use crate::deserializer::{
     types::BamlValueWithFlags,
 };
 
-use super::{ParsingContext, ParsingError, TypeCoercer};
+use super::{ir_ref::match_all_enum_values, ParsingContext, ParsingError, TypeCoercer};
 
 pub(super) fn coerce_array(
     ctx: &ParsingContext,
@@ -39,15 +39,137 @@ pub(super) fn coerce_array(
                 }
             }
         }
-        Some(v) => {
-            flags.add_flag(Flag::SingleToArray);
-            match inner.coerce(&ctx.enter_scope("<implied>"), inner, Some(v)) {
-                Ok(v) => items.push(v),
-                Err(e) => flags.add_flag(Flag::ArrayItemParseError(0, e)),
+        Some(v) => match split_enum_list(ctx, inner, v) {
+            Some(values) => {
+                flags.add_flag(Flag::EnumsFromText(v.to_string()));
+                items = values;
             }
-        }
+            None => {
+                flags.add_flag(Flag::SingleToArray);
+                match inner.coerce(&ctx.enter_scope("<implied>"), inner, Some(v)) {
+                    Ok(v) => items.push(v),
+                    Err(e) => flags.add_flag(Flag::ArrayItemParseError(0, e)),
+                }
+            }
+        },
         None => {}
     };
 
     Ok(BamlValueWithFlags::List(flags, items))
 }
+
+/// For enum lists returned as free text ("Billing, Refunds and Shipping"),
+/// returns every enum value mentioned. `None` unless there are at least two,
+/// in which case a single enum couldn't have been picked anyway.
+fn split_enum_list(
+    ctx: &ParsingContext,
+    inner: &FieldType,
+    value: &crate::jsonish::Value,
+) -> Option<Vec<BamlValueWithFlags>> {
+    let (FieldType::Enum(name), crate::jsonish::Value::String(s)) = (inner, value) else {
+        return None;
+    };
+    let enm = ctx.of.find_enum(name).ok()?;
+
+    let values = match_all_enum_values(enm, s);
+    if values.len() < 2 {
+        return None;
+    }
+
+    Some(
+        values
+            .into_iter()
+            .map(|v| {
+                BamlValueWithFlags::Enum(
+                    name.clone(),
+                    (v.to_string(), DeserializerConditions::new()).into(),
+                )
+            })
+            .collect(),
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use anyhow::Result;
+    use baml_types::BamlValue;
+    use internal_baml_jinja::types::{Enum, Name, OutputFormatContent};
+    use test_log::test;
+
+    fn parse(raw: &str) -> Result<BamlValue> {
+        parse_as(
+            vec![
+                Name::new("Billing".into()),
+                Name::new("Refund".into()),
+                Name::new("RefundRequest".into()),
+                Name::new_with_alias("Shipping".into(), Some("Delivery".into())),
+                Name::new("Other".into()),
+            ],
+            raw,
+        )
+    }
+
+    fn parse_as(values: Vec<Name>, raw: &str) -> Result<BamlValue> {
+        let target = FieldType::List(Box::new(FieldType::Enum("Category".into())));
+        let category = Enum {
+            name: Name::new("Category".into()),
+            values: values.into_iter().map(|v| (v, None)).collect(),
+            fallback: None,
+            synonyms: Default::default(),
+        };
+        let of = OutputFormatContent::new(vec![category], vec![], target.clone());
+        Ok(crate::from_str(&of, &target, raw, false)?.into())
+    }
+
+    fn categories(values: &[&str]) -> BamlValue {
+        BamlValue::List(
+            values
+                .iter()
+                .map(|v| BamlValue::Enum("Category".into(), v.to_string()))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn splits_free_text() -> Result<()> {
+        assert_eq!(
+            parse("delivery, Billing and another refund. Billing again.")?,
+            categories(&["Shipping", "Billing", "Refund"])
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn plurals_match() -> Result<()> {
+        let values = ["Billing", "Refund", "Shipping"];
+        assert_eq!(
+            parse_as(
+                values.map(|v| Name::new(v.into())).into(),
+                "Billing, Refunds and Shipping"
+            )?,
+            categories(&values)
+        );
+        // Not a plural, another word.
+        assert_eq!(
+            parse("Otherwise, Billing and Refunds")?,
+            categories(&["Billing", "Refund"])
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn splits_lines() -> Result<()> {
+        assert_eq!(
+            parse("- RefundRequest\n- Other")?,
+            categories(&["RefundRequest", "Other"])
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn single_value_is_unchanged() -> Result<()> {
+        assert_eq!(parse("Billing")?, categories(&["Billing"]));
+        Ok(())
+    }
+}
@@ -29,12 +29,46 @@ pub(super) fn coerce_optional(
     let mut flags = DeserializerConditions::new();
     match value {
         None | Some(crate::jsonish::Value::Null) => Ok(BamlValueWithFlags::Null(flags)),
-        Some(v) => match inner.coerce(ctx, optional_target, Some(v)) {
-            Ok(v) => Ok(v),
-            Err(e) => {
-                flags.add_flag(Flag::DefaultButHadUnparseableValue(e));
-                Ok(BamlValueWithFlags::Null(flags))
+        Some(v) => {
+            let parsed = inner.coerce(ctx, optional_target, Some(v));
+            if let Some(null) = sentinel_to_null(ctx, v, std::slice::from_ref(&parsed)) {
+                return Ok(null);
             }
-        },
+            match parsed {
+                Ok(v) => Ok(v),
+                Err(e) => {
+                    flags.add_flag(Flag::DefaultButHadUnparseableValue(e));
+                    Ok(BamlValueWithFlags::Null(flags))
+                }
+            }
+        }
     }
 }
+
+/// Null for strings like "N/A" or "unknown" (see `DeserializerOptions::null_sentinels`),
+/// unless one of the non-null candidates matched exactly, e.g. an enum with a
+/// `None` value.
+pub(super) fn sentinel_to_null(
+    ctx: &ParsingContext,
+    value: &crate::jsonish::Value,
+    parsed: &[Result<BamlValueWithFlags, ParsingError>],
+) -> Option<BamlValueWithFlags> {
+    let crate::jsonish::Value::String(s) = value else {
+        return None;
+    };
+    if !ctx.options.is_null_sentinel(s) {
+        return None;
+    }
+
+    let exact_match = parsed.iter().any(|p| match p {
+        Ok(BamlValueWithFlags::String(_) | BamlValueWithFlags::Null(_)) | Err(_) => false,
+        Ok(v) => v.score_with(&ctx.options.scoring) == 0,
+    });
+    if exact_match {
+        return None;
+    }
+
+    Some(BamlValueWithFlags::Null(
+        DeserializerConditions::new().with_flag(Flag::StringToNull(s.clone())),
+    ))
+}
@@ -50,8 +50,8 @@ fn coerce_null(
         Some(crate::jsonish::Value::Null) | None => {
             Ok(BamlValueWithFlags::Null(Default::default()))
         }
-        Some(v) => Ok(BamlValueWithFlags::Null(
-            DeserializerConditions::new().with_flag(Flag::DefaultButHadValue(v.clone())),
+        Some(_) => Ok(BamlValueWithFlags::Null(
+            DeserializerConditions::new().with_flag(Flag::DefaultButHadValue),
         )),
     }
 }
@@ -68,7 +68,7 @@ fn coerce_string(
             }
             crate::jsonish::Value::Null => Err(ctx.error_unexpected_null(target)),
             v => Ok(BamlValueWithFlags::String(
-                (v.to_string(), Flag::JsonToString(v.clone())).into(),
+                (v.to_string(), Flag::JsonToString).into(),
             )),
         }
     } else {
@@ -1,9 +1,12 @@
 use anyhow::Result;
-use internal_baml_core::ir::FieldType;
+use internal_baml_core::ir::{FieldType, TypeValue};
 
 use crate::deserializer::{coercer::array_helper, types::BamlValueWithFlags};
 
-use super::{ParsingContext, ParsingError, TypeCoercer};
+use super::{
+    coerce_optional::sentinel_to_null, ir_ref::is_tagged_as, ParsingContext, ParsingError,
+    TypeCoercer,
+};
 
 pub(super) fn coerce_union(
     ctx: &ParsingContext,
@@ -23,10 +26,155 @@ pub(super) fn coerce_union(
         _ => unreachable!(),
     };
 
+    // A tagged object only needs to be coerced into the class it names.
+    if let Some(option) = value.and_then(|v| tagged_option(ctx, options, v)) {
+        return option.coerce(ctx, union_target, value);
+    }
+
     let parsed = options
         .iter()
         .map(|option| option.coerce(ctx, union_target, value))
         .collect::<Vec<_>>();
 
+    if let Some(v) = value {
+        if options
+            .iter()
+            .any(|o| matches!(o, FieldType::Primitive(TypeValue::Null)))
+        {
+            if let Some(null) = sentinel_to_null(ctx, v, &parsed) {
+                return Ok(null);
+            }
+        }
+    }
+
     array_helper::pick_best(ctx, union_target, &parsed)
 }
+
+/// The class option whose `@@discriminator` tag is set to its name in `value`.
+fn tagged_option<'a>(
+    ctx: &ParsingContext,
+    options: &'a [FieldType],
+    value: &crate::jsonish::Value,
+) -> Option<&'a FieldType> {
+    let crate::jsonish::Value::Object(obj) = value else {
+        return None;
+    };
+    options.iter().find(|option| {
+        let FieldType::Class(name) = option else {
+            return false;
+        };
+        let Ok(class) = ctx.of.find_class(name) else {
+            return false;
+        };
+        class.discriminator.as_ref().is_some_and(|key| {
+            obj.iter()
+                .rev()
+                .find(|(k, _)| k == key)
+                .is_some_and(|(_, v)| is_tagged_as(class, v))
+        })
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{deserializer::deserialize_flags::Flag, DeserializerOptions};
+    use anyhow::Result;
+    use baml_types::{BamlMap, BamlValue};
+    use internal_baml_jinja::types::{Class, Name, OutputFormatContent};
+    use test_log::test;
+
+    fn parse(raw: &str) -> Result<BamlValue> {
+        Ok(parse_with_options(raw, &Default::default())?.into())
+    }
+
+    fn parse_with_options(raw: &str, options: &DeserializerOptions) -> Result<BamlValueWithFlags> {
+        let pet = |name: &str, extra: &str| Class {
+            name: Name::new(name.into()),
+            fields: [
+                ("name", FieldType::Primitive(TypeValue::String)),
+                (
+                    extra,
+                    FieldType::Optional(Box::new(FieldType::Primitive(TypeValue::Int))),
+                ),
+            ]
+            .into_iter()
+            .map(|(n, t)| (Name::new(n.into()), t, None))
+            .collect(),
+            extra_fields: None,
+            discriminator: Some("type".into()),
+        };
+        let target = FieldType::Union(vec![
+            FieldType::Class("Cat".into()),
+            FieldType::Class("Dog".into()),
+        ]);
+        let of = OutputFormatContent::new(
+            vec![],
+            vec![pet("Cat", "lives"), pet("Dog", "age")],
+            target.clone(),
+        );
+        crate::from_str_with_options(&of, &target, raw, false, options)
+    }
+
+    fn pet(class: &str, name: &str, extra: (&str, Option<i64>)) -> BamlValue {
+        BamlValue::Class(
+            class.into(),
+            BamlMap::from_iter([
+                ("name".into(), BamlValue::String(name.into())),
+                (
+                    extra.0.into(),
+                    extra.1.map_or(BamlValue::Null, BamlValue::Int),
+                ),
+            ]),
+        )
+    }
+
+    #[test]
+    fn picks_tagged_class() -> Result<()> {
+        // Both classes fit equally well, the tag decides.
+        assert_eq!(
+            parse(r#"{"type": "dog", "name": "Rex"}"#)?,
+            pet("Dog", "Rex", ("age", None))
+        );
+        assert_eq!(
+            parse(r#"{"name": "Tom", "type": "Cat"}"#)?,
+            pet("Cat", "Tom", ("lives", None))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn untagged_falls_back_to_scoring() -> Result<()> {
+        assert_eq!(
+            parse(r#"{"name": "Rex", "age": 3}"#)?,
+            pet("Dog", "Rex", ("age", Some(3)))
+        );
+        assert_eq!(
+            parse(r#"{"type": "Bird", "name": "Rex", "age": 3}"#)?,
+            pet("Dog", "Rex", ("age", Some(3)))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn alternatives_kept_on_request() -> Result<()> {
+        let raw = r#"{"name": "Rex", "age": 3}"#;
+        let alternatives = |options: &DeserializerOptions| -> Result<_> {
+            let BamlValueWithFlags::Class(_, flags, _) = parse_with_options(raw, options)? else {
+                panic!("Expected class");
+            };
+            Ok(flags.flags.iter().find_map(|f| match f {
+                Flag::FirstMatch(1, alternatives) => Some(alternatives.clone()),
+                _ => None,
+            }))
+        };
+
+        assert!(matches!(alternatives(&Default::default())?, Some(None)));
+        let options = DeserializerOptions {
+            keep_alternatives: true,
+            ..Default::default()
+        };
+        assert!(matches!(alternatives(&options)?, Some(Some(a)) if a.len() == 2));
+        Ok(())
+    }
+}
@@ -20,73 +20,159 @@ impl TypeCoercer for FieldType {
         target: &FieldType,
         value: Option<&crate::jsonish::Value>,
     ) -> Result<BamlValueWithFlags, ParsingError> {
-        match value {
-            Some(crate::jsonish::Value::AnyOf(candidates, primitive)) => {
-                log::debug!(
-                    "scope: {scope} :: coercing to: {name} (current: {current})",
-                    name = target.to_string(),
-                    scope = ctx.display_scope(),
-                    current = value.map(|v| v.r#type()).unwrap_or("<null>".into())
-                );
-                if matches!(target, FieldType::Primitive(TypeValue::String)) {
-                    self.coerce(
-                        ctx,
-                        target,
-                        Some(&crate::jsonish::Value::String(primitive.clone())),
-                    )
-                } else {
-                    array_helper::coerce_array_to_singular(
-                        ctx,
-                        target,
-                        &candidates.iter().collect::<Vec<_>>(),
-                        &|val| self.coerce(ctx, target, Some(val)),
-                    )
-                }
-            }
-            Some(crate::jsonish::Value::Markdown(_t, v)) => {
-                log::debug!(
-                    "scope: {scope} :: coercing to: {name} (current: {current})",
-                    name = target.to_string(),
-                    scope = ctx.display_scope(),
-                    current = value.map(|v| v.r#type()).unwrap_or("<null>".into())
-                );
-                self.coerce(ctx, target, Some(v)).and_then(|mut v| {
-                    v.add_flag(Flag::ObjectFromMarkdown(
-                        if matches!(target, FieldType::Primitive(TypeValue::String)) {
-                            1
-                        } else {
-                            0
-                        },
-                    ));
-
-                    Ok(v)
+        // The caller fails the whole coercion once the time is up, this just
+        // stops the work.
+        if let Err(e) = ctx.deadline.check() {
+            return Err(ctx.error_limit_exceeded(&e));
+        }
+        ctx.memoized(self, target, value, || {
+            coerce_field_type(self, ctx, target, value)
+        })
+    }
+}
+
+fn coerce_field_type(
+    field_type: &FieldType,
+    ctx: &ParsingContext,
+    target: &FieldType,
+    value: Option<&crate::jsonish::Value>,
+) -> Result<BamlValueWithFlags, ParsingError> {
+    match value {
+        Some(v @ crate::jsonish::Value::Spanned(..)) => {
+            coerce_spanned(v, |v| field_type.coerce(ctx, target, Some(v)))
+        }
+        Some(crate::jsonish::Value::AnyOf(candidates, primitive)) => {
+            log::debug!(
+                "scope: {scope} :: coercing to: {name} (current: {current})",
+                name = target.to_string(),
+                scope = ctx.display_scope(),
+                current = value.map(|v| v.r#type()).unwrap_or("<null>".into())
+            );
+            if matches!(target, FieldType::Primitive(TypeValue::String)) {
+                ctx.coerce_temporary(crate::jsonish::Value::String(primitive.clone()), |v| {
+                    field_type.coerce(ctx, target, Some(v))
                 })
+            } else {
+                array_helper::coerce_array_to_singular(
+                    ctx,
+                    target,
+                    &candidates.iter().collect::<Vec<_>>(),
+                    &|val| field_type.coerce(ctx, target, Some(val)),
+                )
             }
-            Some(crate::jsonish::Value::FixedJson(v, fixes)) => {
-                log::debug!(
-                    "scope: {scope} :: coercing to: {name} (current: {current})",
-                    name = target.to_string(),
-                    scope = ctx.display_scope(),
-                    current = value.map(|v| v.r#type()).unwrap_or("<null>".into())
-                );
-                let mut v = self.coerce(ctx, target, Some(v))?;
-                v.add_flag(Flag::ObjectFromFixedJson(fixes.to_vec()));
+        }
+        Some(crate::jsonish::Value::Markdown(_t, v)) => {
+            log::debug!(
+                "scope: {scope} :: coercing to: {name} (current: {current})",
+                name = target.to_string(),
+                scope = ctx.display_scope(),
+                current = value.map(|v| v.r#type()).unwrap_or("<null>".into())
+            );
+            field_type.coerce(ctx, target, Some(v)).and_then(|mut v| {
+                v.add_flag(Flag::ObjectFromMarkdown(
+                    if matches!(target, FieldType::Primitive(TypeValue::String)) {
+                        1
+                    } else {
+                        0
+                    },
+                ));
+
                 Ok(v)
+            })
+        }
+        Some(crate::jsonish::Value::FixedJson(v, fixes)) => {
+            log::debug!(
+                "scope: {scope} :: coercing to: {name} (current: {current})",
+                name = target.to_string(),
+                scope = ctx.display_scope(),
+                current = value.map(|v| v.r#type()).unwrap_or("<null>".into())
+            );
+            let mut v = field_type.coerce(ctx, target, Some(v))?;
+            for fix in fixes {
+                if let crate::jsonish::Fixes::Truncated(open) = fix {
+                    v.add_flag(Flag::Truncated(*open));
+                }
             }
-            _ => match self {
+            v.add_flag(Flag::ObjectFromFixedJson(fixes.to_vec()));
+            Ok(v)
+        }
+        _ => {
+            let coerced = match field_type {
                 FieldType::Primitive(p) => p.coerce(ctx, target, value),
                 FieldType::Enum(e) => IrRef::Enum(e).coerce(ctx, target, value),
                 FieldType::Class(c) => IrRef::Class(c).coerce(ctx, target, value),
-                FieldType::List(_) => coerce_array(ctx, self, value),
-                FieldType::Union(_) => coerce_union(ctx, self, value),
-                FieldType::Optional(_) => coerce_optional(ctx, self, value),
+                FieldType::List(_) => coerce_array(ctx, field_type, value),
+                FieldType::Union(_) => coerce_union(ctx, field_type, value),
+                FieldType::Optional(_) => coerce_optional(ctx, field_type, value),
                 FieldType::Map(_, _) => Err(ctx.error_internal("Map not supported")),
                 FieldType::Tuple(_) => Err(ctx.error_internal("Tuple not supported")),
-            },
+            };
+
+            match (field_type, value) {
+                (
+                    FieldType::Class(_) | FieldType::List(_),
+                    Some(crate::jsonish::Value::String(s)),
+                ) => match coerce_string_as_json(ctx, field_type, target, s) {
+                    // Prefer the decoded value on ties (e.g. against SingleToArray).
+                    Some(Ok(decoded)) => {
+                        array_helper::pick_best(ctx, target, &[Ok(decoded), coerced])
+                    }
+                    _ => coerced,
+                },
+                _ => coerced,
+            }
         }
     }
 }
 
+/// Coerces the value under any spans, and records them on the result.
+pub(super) fn coerce_spanned(
+    value: &crate::jsonish::Value,
+    coerce: impl FnOnce(&crate::jsonish::Value) -> Result<BamlValueWithFlags, ParsingError>,
+) -> Result<BamlValueWithFlags, ParsingError> {
+    match value {
+        crate::jsonish::Value::Spanned(v, span, key) => coerce_spanned(v, coerce).map(|mut v| {
+            v.conditions_mut().set_span(span, key);
+            v
+        }),
+        v => coerce(v),
+    }
+}
+
+/// Structured values are sometimes returned as an escaped JSON string, e.g.
+/// `{"result": "{\"name\": \"x\"}"}`. Returns `None` if the string doesn't
+/// decode into anything but a string.
+fn coerce_string_as_json(
+    ctx: &ParsingContext,
+    field_type: &FieldType,
+    target: &FieldType,
+    s: &str,
+) -> Option<Result<BamlValueWithFlags, ParsingError>> {
+    let trimmed = s.trim();
+    if !trimmed.starts_with('{') && !trimmed.starts_with('[') {
+        return None;
+    }
+
+    // Bounded by the same limits and deadline as the response. Spans would
+    // point into the string rather than the response.
+    let parsed = match crate::parse(trimmed, ctx.options, ctx.deadline).map(|v| v.without_spans()) {
+        Ok(crate::jsonish::Value::String(_)) => return None,
+        Ok(v) => v,
+        Err(e) => {
+            log::debug!("String is not JSON: {:?}", e);
+            return None;
+        }
+    };
+
+    let res = ctx.coerce_temporary(parsed, |parsed| {
+        field_type.coerce(ctx, target, Some(parsed))
+    });
+    Some(res.map(|mut v| {
+        v.add_flag(Flag::ParsedStringAsJson);
+        v
+    }))
+}
+
 impl DefaultValue for FieldType {
     fn default_value(&self, error: Option<&ParsingError>) -> Option<BamlValueWithFlags> {
         let get_flags = || {
@@ -119,3 +205,263 @@ impl DefaultValue for FieldType {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use anyhow::Result;
+    use baml_types::BamlValue;
+    use internal_baml_jinja::types::{Class, Enum, Name, OutputFormatContent};
+    use test_log::test;
+
+    fn string() -> FieldType {
+        FieldType::Primitive(TypeValue::String)
+    }
+
+    fn output_format(target: &FieldType) -> OutputFormatContent {
+        let inner = Class {
+            name: Name::new("Inner".into()),
+            fields: vec![(Name::new("name".into()), string(), None)],
+            extra_fields: None,
+            discriminator: None,
+        };
+        let outer = Class {
+            name: Name::new("Outer".into()),
+            fields: vec![(
+                Name::new("result".into()),
+                FieldType::Class("Inner".into()),
+                None,
+            )],
+            extra_fields: None,
+            discriminator: None,
+        };
+        OutputFormatContent::new(vec![], vec![inner, outer], target.clone())
+    }
+
+    #[test]
+    fn decodes_json_string_field() -> Result<()> {
+        let target = FieldType::Class("Outer".into());
+        let res = crate::from_str(
+            &output_format(&target),
+            &target,
+            r#"{"result": "{\"name\": \"x\"}"}"#,
+            false,
+        )?;
+        let BamlValue::Class(_, fields) = BamlValue::from(res) else {
+            panic!("Expected class");
+        };
+        let Some(BamlValue::Class(_, inner)) = fields.get("result") else {
+            panic!("Expected nested class, got {:?}", fields);
+        };
+        assert_eq!(inner.get("name"), Some(&BamlValue::String("x".into())));
+        Ok(())
+    }
+
+    #[test]
+    fn decodes_json_string_list() -> Result<()> {
+        let target = FieldType::List(Box::new(string()));
+        let res = crate::from_str(
+            &output_format(&target),
+            &target,
+            r#""[\"a\", \"b\"]""#,
+            false,
+        )?;
+        assert_eq!(
+            BamlValue::from(res),
+            BamlValue::List(vec![
+                BamlValue::String("a".into()),
+                BamlValue::String("b".into())
+            ])
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn json_string_respects_limits() -> Result<()> {
+        let target = FieldType::List(Box::new(string()));
+        let raw = r#""[\"a\", \"b\", \"c\"]""#;
+        let options = crate::DeserializerOptions {
+            limits: crate::ParseLimits {
+                max_collection_items: 2,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let res =
+            crate::from_str_with_options(&output_format(&target), &target, raw, false, &options)?;
+        // Too many items to decode, so it's kept as one string.
+        assert_eq!(
+            BamlValue::from(res),
+            BamlValue::List(vec![BamlValue::String(r#"["a", "b", "c"]"#.into())])
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn genuine_string_wins_in_union() -> Result<()> {
+        let target = FieldType::Union(vec![string(), FieldType::Class("Inner".into())]);
+        let res = crate::from_str(
+            &output_format(&target),
+            &target,
+            r#""{\"name\": \"x\"}""#,
+            false,
+        )?;
+        assert_eq!(
+            BamlValue::from(res),
+            BamlValue::String(r#"{"name": "x"}"#.into())
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn memoized_matches_unmemoized() -> Result<()> {
+        let target = FieldType::Union(vec![
+            FieldType::Class("Outer".into()),
+            FieldType::Class("Inner".into()),
+        ]);
+        let of = output_format(&target);
+        let raw = r#"Sure:
+```json
+{"result": {"name": "x"}}
+```
+or maybe {"result": {"name": "x"}, "name": "y"}"#;
+
+        let parse = |memoize| {
+            let options = crate::DeserializerOptions {
+                memoize,
+                ..Default::default()
+            };
+            crate::from_str_with_options(&of, &target, raw, false, &options)
+        };
+        let (memoized, unmemoized) = (parse(true)?, parse(false)?);
+        assert_eq!(memoized.score(), unmemoized.score());
+        assert_eq!(BamlValue::from(memoized), BamlValue::from(unmemoized));
+        Ok(())
+    }
+
+    #[test]
+    fn memoized_decoded_strings() -> Result<()> {
+        // Each decoded string is a different temporary value.
+        let target = FieldType::List(Box::new(FieldType::Union(vec![
+            FieldType::Class("Outer".into()),
+            FieldType::Class("Inner".into()),
+        ])));
+        let of = output_format(&target);
+        let raw =
+            r#"["{\"name\": \"a\"}", "{\"result\": {\"name\": \"b\"}}", "{\"name\": \"c\"}"]"#;
+
+        let res = crate::from_str(&of, &target, raw, false)?;
+        let unmemoized = crate::DeserializerOptions {
+            memoize: false,
+            ..Default::default()
+        };
+        let expected = crate::from_str_with_options(&of, &target, raw, false, &unmemoized)?;
+        let res = BamlValue::from(res);
+        assert_eq!(res, BamlValue::from(expected));
+
+        let inner = |name: &str| {
+            BamlValue::Class(
+                "Inner".into(),
+                BamlMap::from_iter([("name".into(), BamlValue::String(name.into()))]),
+            )
+        };
+        assert_eq!(
+            res,
+            BamlValue::List(vec![
+                inner("a"),
+                BamlValue::Class(
+                    "Outer".into(),
+                    BamlMap::from_iter([("result".into(), inner("b"))])
+                ),
+                inner("c"),
+            ])
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn null_sentinels() -> Result<()> {
+        let int = FieldType::Primitive(TypeValue::Int);
+        let target = FieldType::Class("Person".into());
+        let person = Class {
+            name: Name::new("Person".into()),
+            fields: [
+                ("title", string()),
+                ("nickname", FieldType::Optional(Box::new(string()))),
+                ("age", FieldType::Optional(Box::new(int))),
+                (
+                    "city",
+                    FieldType::Union(vec![string(), FieldType::Primitive(TypeValue::Null)]),
+                ),
+                (
+                    "status",
+                    FieldType::Optional(Box::new(FieldType::Enum("Status".into()))),
+                ),
+            ]
+            .into_iter()
+            .map(|(n, t)| (Name::new(n.into()), t, None))
+            .collect(),
+            extra_fields: None,
+            discriminator: None,
+        };
+        let status = Enum {
+            name: Name::new("Status".into()),
+            values: vec![
+                (Name::new("Active".into()), None),
+                (Name::new("None".into()), None),
+            ],
+            fallback: None,
+            synonyms: Default::default(),
+        };
+        let of = OutputFormatContent::new(vec![status], vec![person], target.clone());
+        let raw = r#"{"title": "N/A", "nickname": " not mentioned. ", "age": "unknown", "city": "-", "status": "none"}"#;
+
+        let options = crate::DeserializerOptions {
+            null_sentinels: ["n/a", "none", "unknown", "-", "not mentioned"]
+                .into_iter()
+                .map(String::from)
+                .collect(),
+            ..Default::default()
+        };
+        let res = crate::from_str_with_options(&of, &target, raw, false, &options)?;
+        let BamlValueWithFlags::Class(_, _, fields) = &res else {
+            panic!("Expected class");
+        };
+        assert!(matches!(&fields["age"], BamlValueWithFlags::Null(f)
+            if f.flags.iter().any(|f| matches!(f, Flag::StringToNull(s) if s == "unknown"))));
+        assert_eq!(
+            BamlValue::from(res),
+            BamlValue::Class(
+                "Person".into(),
+                BamlMap::from_iter([
+                    // Not optional, so kept as is.
+                    ("title".into(), BamlValue::String("N/A".into())),
+                    ("nickname".into(), BamlValue::Null),
+                    ("age".into(), BamlValue::Null),
+                    ("city".into(), BamlValue::Null),
+                    // An enum value that spells the sentinel still wins.
+                    (
+                        "status".into(),
+                        BamlValue::Enum("Status".into(), "None".into())
+                    ),
+                ])
+            )
+        );
+
+        // None by default.
+        let raw = r#"{"title": "NA", "nickname": "Unknown", "age": "unknown", "city": "-", "status": "none"}"#;
+        let res = crate::from_str(&of, &target, raw, false)?;
+        let BamlValue::Class(_, fields) = BamlValue::from(res) else {
+            panic!("Expected class");
+        };
+        assert_eq!(fields["title"], BamlValue::String("NA".into()));
+        assert_eq!(fields["nickname"], BamlValue::String("Unknown".into()));
+        assert_eq!(fields["age"], BamlValue::Null);
+        assert_eq!(fields["city"], BamlValue::String("-".into()));
+        assert_eq!(
+            fields["status"],
+            BamlValue::Enum("Status".into(), "None".into())
+        );
+        Ok(())
+    }
+}
@@ -1,3 +1,5 @@
+use std::borrow::Cow;
+
 use anyhow::Result;
 use baml_types::BamlMap;
 use internal_baml_core::ir::FieldType;
@@ -6,6 +8,7 @@ use internal_baml_jinja::types::{Class, Name};
 use crate::deserializer::{
     coercer::{array_helper, DefaultValue, ParsingError, TypeCoercer},
     deserialize_flags::{DeserializerConditions, Flag},
+    deserialize_options::DuplicateKeyPolicy,
     types::BamlValueWithFlags,
 };
 
@@ -27,8 +30,15 @@ impl TypeCoercer for Class {
             scope = ctx.display_scope(),
             current = value.map(|v| v.r#type()).unwrap_or("<null>".into())
         );
+        // The @@extra_fields field isn't matched, it collects the keys nothing else matched.
+        let (extra_field, fields): (Vec<_>, Vec<_>) = self
+            .fields
+            .iter()
+            .partition(|(name, ..)| self.extra_fields.as_deref() == Some(name.real_name()));
+        let mut extra_values = BamlMap::new();
+
         let (optional, required): (Vec<_>, Vec<_>) =
-            self.fields.iter().partition(|f| f.1.is_optional());
+            fields.iter().copied().partition(|f| f.1.is_optional());
         let mut optional_values = optional
             .iter()
             .map(|(f, ..)| (f.real_name().to_string(), None))
@@ -47,24 +57,75 @@ impl TypeCoercer for Class {
                 // Do nothing
             }
             Some(crate::jsonish::Value::Object(obj)) => {
+                // Group repeated keys, they're resolved by the duplicate key policy.
+                let mut entries = BamlMap::<&String, Vec<&crate::jsonish::Value>>::new();
+                for (key, v) in obj {
+                    // The @@discriminator tag isn't a field. A tag naming another
+                    // class is left in, so it counts against this one.
+                    if self.discriminator.as_ref() == Some(key) && is_tagged_as(self, v) {
+                        continue;
+                    }
+                    entries.entry(key).or_default().push(v);
+                }
+
                 // match keys, if that fails, then do something fancy later.
-                obj.iter().for_each(|(key, v)| {
-                    if let Some(field) = self
-                        .fields
+                let mut unmatched = vec![];
+                for (key, values) in entries {
+                    if let Some(field) = fields
                         .iter()
                         .find(|(name, ..)| name.rendered_name().trim() == key)
                     {
+                        let v = resolve_duplicates(ctx, field, key, &values, &mut flags)?;
                         let scope = ctx.enter_scope(field.0.real_name());
-                        let parsed = field.1.coerce(&scope, &field.1, Some(v));
+                        let parsed = coerce_field(&scope, field, v);
                         update_map(&mut required_values, &mut optional_values, field, parsed);
                     } else {
-                        flags.add_flag(Flag::ExtraKey(key.clone(), v.clone()));
+                        unmatched.push((key, values));
                     }
-                });
+                }
+
+                // Only after all exact matches, so they take priority.
+                for (key, values) in unmatched {
+                    let is_unset = |(name, t, ..): &FieldValue| {
+                        let map = if t.is_optional() {
+                            &optional_values
+                        } else {
+                            &required_values
+                        };
+                        matches!(map.get(name.real_name()), Some(None))
+                    };
+                    match normalized_field(&fields, key, is_unset, ctx.options.fuzzy_key_threshold)
+                    {
+                        Some(field) => {
+                            flags.add_flag(Flag::KeyNormalized(
+                                key.clone(),
+                                field.0.rendered_name().into(),
+                            ));
+                            let v = resolve_duplicates(ctx, field, key, &values, &mut flags)?;
+                            let scope = ctx.enter_scope(field.0.real_name());
+                            let parsed = coerce_field(&scope, field, v);
+                            update_map(&mut required_values, &mut optional_values, field, parsed);
+                        }
+                        // Keys collected into `@@extra_fields` are expected, so they
+                        // aren't penalized.
+                        None => match extra_field.first() {
+                            Some(extra_field) => {
+                                let v =
+                                    resolve_duplicates(ctx, extra_field, key, &values, &mut flags)?;
+                                extra_values.insert(key.clone(), raw_value(&v));
+                            }
+                            None => {
+                                for v in values {
+                                    flags.add_flag(Flag::ExtraKey(key.clone(), v.span().cloned()));
+                                }
+                            }
+                        },
+                    }
+                }
             }
             Some(crate::jsonish::Value::Array(items)) => {
-                if self.fields.len() == 1 {
-                    let field = &self.fields[0];
+                if fields.len() == 1 {
+                    let field = fields[0];
                     let scope = ctx.enter_scope(&format!("<implied:{}>", field.0.real_name()));
                     let parsed = match field.1.coerce(&scope, &field.1, value) {
                         Ok(mut v) => {
@@ -88,8 +149,8 @@ impl TypeCoercer for Class {
             }
             Some(x) => {
                 // If the class has a single field, then we can try to coerce it directly
-                if self.fields.len() == 1 {
-                    let field = &self.fields[0];
+                if fields.len() == 1 {
+                    let field = fields[0];
                     let scope = ctx.enter_scope(&format!("<implied:{}>", field.0.real_name()));
                     let parsed = match field.1.coerce(&scope, &field.1, Some(x)) {
                         Ok(mut v) => {
@@ -105,7 +166,7 @@ impl TypeCoercer for Class {
 
         // Check what we have / what we need
         {
-            self.fields.iter().for_each(|(field_name, t, ..)| {
+            fields.iter().for_each(|(field_name, t, ..)| {
                 if t.is_optional() {
                     if let Some(v) = optional_values.get(field_name.real_name()) {
                         let next = match v {
@@ -118,6 +179,15 @@ impl TypeCoercer for Class {
                                 );
                                 t.default_value(Some(e))
                             }
+                            // Keep the penalty on the class so dropping the field
+                            // doesn't change which candidate wins.
+                            None if ctx.options.omit_missing_optional_fields => {
+                                flags.add_flag(Flag::OmittedMissingField(
+                                    field_name.real_name().to_string(),
+                                ));
+                                optional_values.shift_remove(field_name.real_name());
+                                return;
+                            }
                             // If we're missing a field, thats ok!
                             None => Some(BamlValueWithFlags::Null(
                                 DeserializerConditions::new().with_flag(Flag::DefaultFromNoValue),
@@ -184,9 +254,9 @@ impl TypeCoercer for Class {
 
             let unparsed_required_fields = required_values
                 .iter()
-                .filter_map(|(k, v)| match v {
+                .filter_map(|(_, v)| match v {
                     Some(Ok(_)) => None,
-                    Some(Err(e)) => Some((k.clone(), e.to_string())),
+                    Some(Err(e)) => Some(e),
                     None => None,
                 })
                 .collect::<Vec<_>>();
@@ -202,7 +272,7 @@ impl TypeCoercer for Class {
             if !missing_required_fields.is_empty() || !unparsed_required_fields.is_empty() {
                 if completed_cls.is_empty() {
                     return Err(ctx.error_missing_required_field(
-                        &unparsed_required_fields,
+                        unparsed_required_fields,
                         &missing_required_fields,
                     ));
                 }
@@ -229,6 +299,12 @@ impl TypeCoercer for Class {
                             ),
                         }
                     }))
+                    .chain(self.extra_fields.clone().map(|name| {
+                        (
+                            name,
+                            BamlValueWithFlags::Map(Default::default(), extra_values),
+                        )
+                    }))
                     .collect::<BamlMap<String, _>>();
 
                 completed_cls.insert(
@@ -248,6 +324,129 @@ impl TypeCoercer for Class {
     }
 }
 
+/// Unknown keys collected by `@@extra_fields` keep strings as is, and anything
+/// else as JSON.
+fn raw_value(value: &crate::jsonish::Value) -> (DeserializerConditions, BamlValueWithFlags) {
+    let raw = match value.unspanned() {
+        crate::jsonish::Value::String(s) => s.clone(),
+        v => serde_json::Value::from(v).to_string(),
+    };
+    (
+        DeserializerConditions::new(),
+        BamlValueWithFlags::String(raw.into()),
+    )
+}
+
+/// Picks the value to coerce for a key that may have been repeated.
+fn resolve_duplicates<'v>(
+    ctx: &ParsingContext,
+    (_, t, ..): &FieldValue,
+    key: &str,
+    values: &[&'v crate::jsonish::Value],
+    flags: &mut DeserializerConditions,
+) -> Result<Cow<'v, crate::jsonish::Value>, ParsingError> {
+    let (first, last) = match values {
+        [] => unreachable!("every key has at least one value"),
+        [v] => return Ok(Cow::Borrowed(*v)),
+        [first, .., last] => (*first, *last),
+    };
+
+    flags.add_flag(Flag::DuplicateKey(key.into(), values.len()));
+    let is_list = match t {
+        FieldType::Optional(inner) => matches!(**inner, FieldType::List(_)),
+        t => matches!(t, FieldType::List(_)),
+    };
+    match ctx.options.duplicate_keys {
+        DuplicateKeyPolicy::FirstWins => Ok(Cow::Borrowed(first)),
+        DuplicateKeyPolicy::MergeIntoList if is_list => {
+            Ok(Cow::Owned(crate::jsonish::Value::Array(
+                values
+                    .iter()
+                    .flat_map(|v| match v.unspanned() {
+                        crate::jsonish::Value::Array(items) => items.clone(),
+                        _ => vec![(*v).clone()],
+                    })
+                    .collect(),
+            )))
+        }
+        DuplicateKeyPolicy::LastWins | DuplicateKeyPolicy::MergeIntoList => Ok(Cow::Borrowed(last)),
+        DuplicateKeyPolicy::Error => {
+            Err(ctx.enter_scope(key).error_duplicate_key(key, values.len()))
+        }
+    }
+}
+
+/// A merged list of repeated values is made here, not part of the response.
+fn coerce_field(
+    scope: &ParsingContext,
+    (_, t, ..): &FieldValue,
+    value: Cow<'_, crate::jsonish::Value>,
+) -> Result<BamlValueWithFlags, ParsingError> {
+    match value {
+        Cow::Borrowed(v) => t.coerce(scope, t, Some(v)),
+        Cow::Owned(v) => scope.coerce_temporary(v, |v| t.coerce(scope, t, Some(v))),
+    }
+}
+
+/// `firstName`, `First Name`, `first-name` and `FIRST_NAME` all become `firstname`.
+fn normalize_key(key: &str) -> String {
+    key.chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// Finds the field `key` refers to after normalizing case and separators, or
+/// failing that by edit distance (if `fuzzy_threshold` is set). Only fields
+/// that `is_candidate` are considered, and ties match nothing.
+fn normalized_field<'a>(
+    fields: &[&'a FieldValue],
+    key: &str,
+    is_candidate: impl Fn(&FieldValue) -> bool,
+    fuzzy_threshold: Option<f64>,
+) -> Option<&'a FieldValue> {
+    let key = normalize_key(key);
+    if key.is_empty() {
+        return None;
+    }
+
+    let candidates = fields
+        .iter()
+        .copied()
+        .filter(|f| is_candidate(f))
+        .map(|f| (normalize_key(f.0.rendered_name()), f))
+        .collect::<Vec<_>>();
+
+    if let Some((_, field)) = candidates.iter().find(|(name, _)| *name == key) {
+        return Some(field);
+    }
+
+    let threshold = fuzzy_threshold?;
+    let mut best: Option<(usize, &FieldValue)> = None;
+    let mut tied = false;
+    for (name, field) in candidates {
+        let distance = strsim::osa_distance(&key, &name);
+        let len = key.chars().count().max(name.chars().count());
+        if 1.0 - (distance as f64 / len as f64) < threshold {
+            continue;
+        }
+        match best {
+            Some((best_distance, _)) if distance > best_distance => {}
+            Some((best_distance, _)) if distance == best_distance => tied = true,
+            _ => {
+                best = Some((distance, field));
+                tied = false;
+            }
+        }
+    }
+
+    if tied {
+        None
+    } else {
+        best.map(|(_, field)| field)
+    }
+}
+
 fn update_map<'a>(
     required_values: &'a mut BamlMap<String, Option<Result<BamlValueWithFlags, ParsingError>>>,
     optional_values: &'a mut BamlMap<String, Option<Result<BamlValueWithFlags, ParsingError>>>,
@@ -260,7 +459,7 @@ fn update_map<'a>(
         required_values
     };
     let key = name.real_name();
-    // TODO: @hellovai plumb this via some flag?
+    // Repeated keys were already resolved by the duplicate key policy.
     match map.get(key) {
         Some(Some(_)) => {
             // DO NOTHING (keep first value)
@@ -274,3 +473,342 @@ fn update_map<'a>(
         }
     }
 }
+
+/// Whether `value` is the `@@discriminator` tag of `class`, i.e. its name.
+pub(crate) fn is_tagged_as(class: &Class, value: &crate::jsonish::Value) -> bool {
+    let crate::jsonish::Value::String(tag) = value.unspanned() else {
+        return false;
+    };
+    let tag = tag.trim();
+    tag.eq_ignore_ascii_case(class.name.rendered_name())
+        || tag.eq_ignore_ascii_case(class.name.real_name())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::DeserializerOptions;
+    use baml_types::{BamlValue, TypeValue};
+    use internal_baml_jinja::types::OutputFormatContent;
+    use test_log::test;
+
+    fn parse_class(
+        name: &str,
+        fields: &[(&str, FieldType)],
+        raw: &str,
+        options: &DeserializerOptions,
+    ) -> anyhow::Result<BamlValue> {
+        parse_class_with_extra_fields(name, fields, None, raw, options)
+    }
+
+    fn parse_class_with_extra_fields(
+        name: &str,
+        fields: &[(&str, FieldType)],
+        extra_fields: Option<&str>,
+        raw: &str,
+        options: &DeserializerOptions,
+    ) -> anyhow::Result<BamlValue> {
+        let target = FieldType::Class(name.into());
+        let class = Class {
+            name: Name::new(name.into()),
+            fields: fields
+                .iter()
+                .map(|(f, t)| (Name::new(f.to_string()), t.clone(), None))
+                .collect(),
+            extra_fields: extra_fields.map(Into::into),
+            discriminator: None,
+        };
+        let of = OutputFormatContent::new(vec![], vec![class], target.clone());
+        Ok(crate::from_str_with_options(&of, &target, raw, false, options)?.into())
+    }
+
+    fn parse(raw: &str, options: &DeserializerOptions) -> anyhow::Result<BamlValue> {
+        let string = FieldType::Primitive(TypeValue::String);
+        parse_class(
+            "Person",
+            &[("first_name", string.clone()), ("last_name", string)],
+            raw,
+            options,
+        )
+    }
+
+    fn person(first: &str, last: &str) -> BamlValue {
+        BamlValue::Class(
+            "Person".into(),
+            BamlMap::from_iter([
+                ("first_name".into(), BamlValue::String(first.into())),
+                ("last_name".into(), BamlValue::String(last.into())),
+            ]),
+        )
+    }
+
+    #[test]
+    fn normalizes_keys() -> anyhow::Result<()> {
+        for raw in [
+            r#"{"firstName": "A", "LastName": "B"}"#,
+            r#"{"First Name": "A", "last-name": "B"}"#,
+            r#"{"FIRST_NAME": "A", "last_name": "B"}"#,
+        ] {
+            assert_eq!(
+                parse(raw, &Default::default())?,
+                person("A", "B"),
+                "{}",
+                raw
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn exact_key_wins() -> anyhow::Result<()> {
+        assert_eq!(
+            parse(
+                r#"{"firstName": "X", "first_name": "A", "last_name": "B"}"#,
+                &Default::default()
+            )?,
+            person("A", "B")
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn fuzzy_keys_are_opt_in() -> anyhow::Result<()> {
+        let raw = r#"{"frist_name": "A", "last_nmae": "B"}"#;
+        assert!(parse(raw, &Default::default()).is_err());
+
+        let options = DeserializerOptions {
+            fuzzy_key_threshold: Some(0.8),
+            ..Default::default()
+        };
+        assert_eq!(parse(raw, &options)?, person("A", "B"));
+        Ok(())
+    }
+
+    #[test]
+    fn duplicate_key_policies() -> anyhow::Result<()> {
+        let raw = r#"{"first_name": "A", "last_name": "B", "first_name": "C"}"#;
+        let with = |duplicate_keys| DeserializerOptions {
+            duplicate_keys,
+            ..Default::default()
+        };
+
+        assert_eq!(parse(raw, &Default::default())?, person("C", "B"));
+        assert_eq!(
+            parse(raw, &with(DuplicateKeyPolicy::FirstWins))?,
+            person("A", "B")
+        );
+        assert_eq!(
+            parse(raw, &with(DuplicateKeyPolicy::MergeIntoList))?,
+            person("C", "B")
+        );
+        assert!(parse(raw, &with(DuplicateKeyPolicy::Error)).is_err());
+
+        // Also applies to objects the fixing parser had to repair.
+        assert_eq!(
+            parse(
+                r#"{first_name: "A", last_name: "B", first_name: "C""#,
+                &with(DuplicateKeyPolicy::FirstWins)
+            )?,
+            person("A", "B")
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn duplicate_keys_merge_into_list() -> anyhow::Result<()> {
+        let tags = FieldType::List(Box::new(FieldType::Primitive(TypeValue::String)));
+        let options = DeserializerOptions {
+            duplicate_keys: DuplicateKeyPolicy::MergeIntoList,
+            ..Default::default()
+        };
+        assert_eq!(
+            parse_class(
+                "Tagged",
+                &[("tags", tags)],
+                r#"{"tags": "a", "tags": ["b", "c"]}"#,
+                &options
+            )?,
+            BamlValue::Class(
+                "Tagged".into(),
+                BamlMap::from_iter([(
+                    "tags".into(),
+                    BamlValue::List(vec![
+                        BamlValue::String("a".into()),
+                        BamlValue::String("b".into()),
+                        BamlValue::String("c".into()),
+                    ])
+                )])
+            )
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn collects_extra_fields() -> anyhow::Result<()> {
+        let string = FieldType::Primitive(TypeValue::String);
+        let map = FieldType::Map(Box::new(string.clone()), Box::new(string.clone()));
+        let parse = |raw| {
+            parse_class_with_extra_fields(
+                "Person",
+                &[("name", string.clone()), ("other", map.clone())],
+                Some("other"),
+                raw,
+                &Default::default(),
+            )
+        };
+        let person = |other: &[(&str, &str)]| {
+            BamlValue::Class(
+                "Person".into(),
+                BamlMap::from_iter([
+                    ("name".into(), BamlValue::String("A".into())),
+                    (
+                        "other".into(),
+                        BamlValue::Map(
+                            other
+                                .iter()
+                                .map(|(k, v)| (k.to_string(), BamlValue::String(v.to_string())))
+                                .collect(),
+                        ),
+                    ),
+                ]),
+            )
+        };
+
+        assert_eq!(
+            parse(r#"{"name": "A", "age": 30, "city": "Paris", "tags": ["x"]}"#)?,
+            person(&[("age", "30"), ("city", "Paris"), ("tags", r#"["x"]"#)])
+        );
+        // The field itself isn't expected in the response.
+        assert_eq!(parse(r#"{"name": "A"}"#)?, person(&[]));
+        // Single field classes still work.
+        assert_eq!(parse(r#""A""#)?, person(&[]));
+
+        // Repeated keys follow the duplicate key policy, as for any field.
+        let raw = r#"{"name": "A", "city": "Paris", "city": "Rome"}"#;
+        assert_eq!(parse(raw)?, person(&[("city", "Rome")]));
+        let parse_with = |duplicate_keys| {
+            parse_class_with_extra_fields(
+                "Person",
+                &[("name", string.clone()), ("other", map.clone())],
+                Some("other"),
+                raw,
+                &DeserializerOptions {
+                    duplicate_keys,
+                    ..Default::default()
+                },
+            )
+        };
+        assert_eq!(
+            parse_with(DuplicateKeyPolicy::FirstWins)?,
+            person(&[("city", "Paris")])
+        );
+        assert!(parse_with(DuplicateKeyPolicy::Error).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn collected_extra_fields_are_not_penalized() -> anyhow::Result<()> {
+        let string = FieldType::Primitive(TypeValue::String);
+        let map = FieldType::Map(Box::new(string.clone()), Box::new(string.clone()));
+        let target = FieldType::Class("Person".into());
+        let class = |extra_fields: Option<&str>| Class {
+            name: Name::new("Person".into()),
+            fields: vec![
+                (Name::new("name".into()), string.clone(), None),
+                (Name::new("other".into()), map.clone(), None),
+            ],
+            extra_fields: extra_fields.map(Into::into),
+            discriminator: None,
+        };
+        let score = |extra_fields| -> anyhow::Result<i32> {
+            let of = OutputFormatContent::new(vec![], vec![class(extra_fields)], target.clone());
+            let raw = r#"{"name": "A", "other": {}, "city": "Paris"}"#;
+            Ok(
+                crate::from_str_with_options(&of, &target, raw, false, &Default::default())?
+                    .score(),
+            )
+        };
+
+        assert_eq!(score(Some("other"))?, 0);
+        assert!(score(None)? > 0);
+        Ok(())
+    }
+
+    #[test]
+    fn extra_key_points_at_the_value() -> anyhow::Result<()> {
+        let string = FieldType::Primitive(TypeValue::String);
+        let target = FieldType::Class("Person".into());
+        let class = Class {
+            name: Name::new("Person".into()),
+            fields: vec![
+                (Name::new("name".into()), string.clone(), None),
+                (Name::new("city".into()), string, None),
+            ],
+            extra_fields: None,
+            discriminator: None,
+        };
+        let of = OutputFormatContent::new(vec![], vec![class], target.clone());
+        let raw = r#"{"name": "A", "city": "Paris", "age": [1, 2]}"#;
+
+        let res = crate::from_str(&of, &target, raw, false)?;
+        let spans = res
+            .conditions()
+            .flags
+            .iter()
+            .filter_map(|f| match f {
+                Flag::ExtraKey(key, span) => Some((key.as_str(), span.clone().map(|s| &raw[s]))),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(spans, [("age", Some("[1, 2]"))]);
+        Ok(())
+    }
+
+    #[test]
+    fn missing_optional_fields() -> anyhow::Result<()> {
+        let optional = FieldType::Optional(Box::new(FieldType::Primitive(TypeValue::String)));
+        let fields = [("name", optional.clone()), ("note", optional)];
+        let raw = r#"{"name": null}"#;
+        let omit = DeserializerOptions {
+            omit_missing_optional_fields: true,
+            ..Default::default()
+        };
+
+        let target = FieldType::Class("Patch".into());
+        let class = Class {
+            name: Name::new("Patch".into()),
+            fields: fields
+                .iter()
+                .map(|(f, t)| (Name::new(f.to_string()), t.clone(), None))
+                .collect(),
+            extra_fields: None,
+            discriminator: None,
+        };
+        let of = OutputFormatContent::new(vec![], vec![class], target.clone());
+        let BamlValueWithFlags::Class(_, _, values) = crate::from_str(&of, &target, raw, false)?
+        else {
+            panic!("expected a class");
+        };
+        assert!(!values["name"].is_missing());
+        assert!(values["note"].is_missing());
+
+        assert_eq!(
+            parse_class("Patch", &fields, raw, &omit)?,
+            BamlValue::Class(
+                "Patch".into(),
+                BamlMap::from_iter([("name".into(), BamlValue::Null)]),
+            )
+        );
+        assert_eq!(
+            parse_class("Patch", &fields, raw, &Default::default())?,
+            BamlValue::Class(
+                "Patch".into(),
+                BamlMap::from_iter([
+                    ("name".into(), BamlValue::Null),
+                    ("note".into(), BamlValue::Null),
+                ]),
+            )
+        );
+        Ok(())
+    }
+}
@@ -14,17 +14,18 @@ fn candidates<'a>(enm: &'a Enum) -> Vec<(&'a str, Vec<String>)> {
     enm.values
         .iter()
         .map(|(name, desc)| {
-            (
-                name.real_name(),
-                match desc.as_ref().map(|d| d.trim()) {
-                    Some(d) if !d.is_empty() => vec![
-                        name.rendered_name().into(),
-                        d.into(),
-                        format!("{}: {}", name.rendered_name(), d),
-                    ],
-                    _ => vec![name.rendered_name().into()],
-                },
-            )
+            let mut valid_values = match desc.as_ref().map(|d| d.trim()) {
+                Some(d) if !d.is_empty() => vec![
+                    name.rendered_name().into(),
+                    d.into(),
+                    format!("{}: {}", name.rendered_name(), d),
+                ],
+                _ => vec![name.rendered_name().into()],
+            };
+            if let Some(synonyms) = enm.synonyms.get(name.real_name()) {
+                valid_values.extend(synonyms.iter().cloned());
+            }
+            (name.real_name(), valid_values)
         })
         .collect()
 }
@@ -55,11 +56,11 @@ impl TypeCoercer for Enum {
         let context = match value {
             crate::jsonish::Value::String(s) => s.clone(),
             crate::jsonish::Value::AnyOf(_, s) => {
-                flags.add_flag(Flag::ObjectToString(value.clone()));
+                flags.add_flag(Flag::ObjectToString);
                 s.clone()
             }
             v => {
-                flags.add_flag(Flag::ObjectToString(v.clone()));
+                flags.add_flag(Flag::ObjectToString);
                 format!("{}", v)
             }
         };
@@ -67,6 +68,34 @@ impl TypeCoercer for Enum {
         let candidates = candidates(self);
 
         let context = context.trim();
+        let raw = context.to_string();
+
+        if let Some(idx) = ctx
+            .options
+            .enum_ordinals
+            .then(|| parse_ordinal(context))
+            .flatten()
+        {
+            if let Some((name, _)) = idx.checked_sub(1).and_then(|i| self.values.get(i)) {
+                // "2" may also be the name of some other value.
+                let ordinal = idx.to_string();
+                let by_name = candidates.iter().find_map(|(e, valid_values)| {
+                    valid_values
+                        .iter()
+                        .any(|v| v.eq_ignore_ascii_case(context) || *v == ordinal)
+                        .then_some(*e)
+                });
+                if let Some(e) = by_name.filter(|e| *e != name.real_name()) {
+                    return Err(ctx.error_too_many_matches(target, [e, name.real_name()]));
+                }
+
+                flags.add_flag(Flag::EnumFromOrdinal(idx));
+                return Ok(BamlValueWithFlags::Enum(
+                    self.name.real_name().into(),
+                    (name.real_name().to_string(), flags).into(),
+                ));
+            }
+        }
 
         if let Some(e) = enum_match_strategy(&context, &candidates, &mut flags) {
             if let Some(mismatch) = flags.flags.iter().find_map(|f| match f {
@@ -112,16 +141,161 @@ impl TypeCoercer for Enum {
             ));
         }
 
-        Err(ctx.error_unexpected_type(target, &value))
+        if let Some((distance, matches)) = ctx
+            .options
+            .fuzzy_enum_threshold
+            .and_then(|threshold| fuzzy_match_strategy(&context, &candidates, threshold))
+        {
+            if matches.len() > 1 {
+                return Err(ctx.error_too_many_matches(target, matches));
+            }
+            flags.add_flag(Flag::FuzzyMatch(distance));
+            return Ok(BamlValueWithFlags::Enum(
+                self.name.real_name().into(),
+                (matches[0].to_string(), flags).into(),
+            ));
+        }
+
+        // Only text is an answer that can be missing from the enum. An object or
+        // a list is the wrong shape, e.g. a class in a union with the enum.
+        let is_text = matches!(
+            value.unspanned(),
+            crate::jsonish::Value::String(_)
+                | crate::jsonish::Value::Number(_)
+                | crate::jsonish::Value::Boolean(_)
+        );
+        if let Some(fallback) = self.fallback.as_ref().filter(|_| is_text) {
+            flags.add_flag(Flag::EnumFallback(raw));
+            return Ok(BamlValueWithFlags::Enum(
+                self.name.real_name().into(),
+                (fallback.clone(), flags).into(),
+            ));
+        }
+
+        Err(ctx.error_enum_no_match(target, value))
     }
 }
 
+/// Every distinct enum value mentioned in `value_str`, in order of first mention.
+/// Used when a list of enums is returned as free text, e.g. "Billing and Refunds".
+pub(crate) fn match_all<'a>(enm: &'a Enum, value_str: &str) -> Vec<&'a str> {
+    // Candidates are matched ascii-case-insensitively, so byte offsets are shared.
+    let haystack = value_str.to_ascii_lowercase();
+    let is_word_char = |c: Option<char>| c.is_some_and(char::is_alphanumeric);
+
+    let mut matches = candidates(enm)
+        .into_iter()
+        .flat_map(|(e, valid_values)| {
+            let haystack = &haystack;
+            valid_values
+                .into_iter()
+                .filter(|v| !v.is_empty())
+                .flat_map(move |v| {
+                    let needle = v.to_ascii_lowercase();
+                    haystack
+                        .match_indices(needle.as_str())
+                        .map(|(start, m)| (start, start + m.len(), e))
+                        .collect::<Vec<_>>()
+                })
+        })
+        // Only whole words, so "Other" doesn't match inside "another". A plural
+        // ("Refunds", "Businesses") still counts as the word.
+        .filter_map(|(start, end, e)| {
+            if is_word_char(haystack[..start].chars().next_back()) {
+                return None;
+            }
+            ["", "s", "es"].iter().find_map(|suffix| {
+                let end = end + suffix.len();
+                (haystack[end - suffix.len()..].starts_with(suffix)
+                    && !is_word_char(haystack[end..].chars().next()))
+                .then_some((start, end, e))
+            })
+        })
+        .collect::<Vec<_>>();
+
+    // Earliest first, then longest, so "Refund" doesn't also match inside "Refund Request".
+    matches.sort_by(|a, b| a.0.cmp(&b.0).then(b.1.cmp(&a.1)));
+
+    let mut result: Vec<&str> = vec![];
+    let mut claimed = 0;
+    for (start, end, e) in matches {
+        if start < claimed {
+            continue;
+        }
+        claimed = end;
+        if !result.contains(&e) {
+            result.push(e);
+        }
+    }
+    result
+}
+
+/// "2", "2.", "#2", "option 2" -> 2
+fn parse_ordinal(s: &str) -> Option<usize> {
+    let s = s.to_ascii_lowercase();
+    let digits = ["option", "choice", "number", "no.", "#"]
+        .iter()
+        .fold(s.as_str(), |s, prefix| {
+            s.strip_prefix(prefix).map_or(s, str::trim_start)
+        })
+        .trim_end_matches(['.', ')', ':']);
+
+    if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    digits.parse().ok()
+}
+
 fn strip_punctuation(s: &str) -> String {
     s.chars()
         .filter(|c| c.is_alphanumeric() || *c == '-' || *c == '_')
         .collect::<String>()
 }
 
+/// Last resort for typos and separator differences ("Recieved", "in-progress" vs
+/// `IN_PROGRESS`). Returns the smallest edit distance with a similarity of at
+/// least `threshold`, and every enum value at that distance.
+fn fuzzy_match_strategy<'a>(
+    value_str: &str,
+    candidates: &'a [(&'a str, Vec<String>)],
+    threshold: f64,
+) -> Option<(usize, Vec<&'a str>)> {
+    let normalize = |s: &str| {
+        s.chars()
+            .filter(|c| c.is_alphanumeric())
+            .flat_map(char::to_lowercase)
+            .collect::<String>()
+    };
+
+    let value = normalize(value_str);
+    if value.is_empty() {
+        return None;
+    }
+
+    let mut best: Option<(usize, Vec<&str>)> = None;
+    for (e, valid_values) in candidates {
+        let Some(distance) = valid_values
+            .iter()
+            .filter_map(|v| {
+                let v = normalize(v);
+                let distance = strsim::osa_distance(&value, &v);
+                let len = value.chars().count().max(v.chars().count());
+                (1.0 - distance as f64 / len as f64 >= threshold).then_some(distance)
+            })
+            .min()
+        else {
+            continue;
+        };
+
+        match &mut best {
+            Some((best_distance, _)) if distance > *best_distance => {}
+            Some((best_distance, matches)) if distance == *best_distance => matches.push(e),
+            _ => best = Some((distance, vec![e])),
+        }
+    }
+    best
+}
+
 fn enum_match_strategy<'a>(
     value_str: &str,
     candidates: &'a Vec<(&'a str, Vec<String>)>,
@@ -200,3 +374,254 @@ fn enum_match_strategy<'a>(
 
     None
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::DeserializerOptions;
+    use anyhow::Result;
+    use baml_types::BamlValue;
+    use internal_baml_jinja::types::{Name, OutputFormatContent};
+    use test_log::test;
+
+    fn parse(values: &[&str], raw: &str, enum_ordinals: bool) -> Result<BamlValue> {
+        parse_with(
+            values,
+            raw,
+            &DeserializerOptions {
+                enum_ordinals,
+                ..Default::default()
+            },
+        )
+    }
+
+    fn parse_with(values: &[&str], raw: &str, options: &DeserializerOptions) -> Result<BamlValue> {
+        Ok(parse_with_fallback(values, None, raw, options)?.into())
+    }
+
+    fn parse_with_fallback(
+        values: &[&str],
+        fallback: Option<&str>,
+        raw: &str,
+        options: &DeserializerOptions,
+    ) -> Result<BamlValueWithFlags> {
+        let target = FieldType::Enum("Category".into());
+        let category = Enum {
+            name: Name::new("Category".into()),
+            values: values
+                .iter()
+                .map(|v| (Name::new(v.to_string()), None))
+                .collect(),
+            fallback: fallback.map(Into::into),
+            synonyms: Default::default(),
+        };
+        let of = OutputFormatContent::new(vec![category], vec![], target.clone());
+        crate::from_str_with_options(&of, &target, raw, false, options)
+    }
+
+    #[test]
+    fn ordinals() {
+        for (input, expected) in [
+            ("2", Some(2)),
+            ("2.", Some(2)),
+            ("#2", Some(2)),
+            ("Option 2", Some(2)),
+            ("option #12)", Some(12)),
+            ("2 apples", None),
+            ("option", None),
+            ("-1", None),
+        ] {
+            assert_eq!(parse_ordinal(input), expected, "{}", input);
+        }
+    }
+
+    #[test]
+    fn ordinal_answers() -> Result<()> {
+        let values = ["Billing", "Refund", "Shipping"];
+        for raw in ["2", "#2", "Option 2", "\"2.\""] {
+            assert_eq!(
+                parse(&values, raw, true)?,
+                BamlValue::Enum("Category".into(), "Refund".into()),
+                "{}",
+                raw
+            );
+        }
+        assert!(parse(&values, "4", true).is_err());
+        assert!(parse(&values, "2", false).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn ordinal_colliding_with_name() -> Result<()> {
+        let values = ["5", "1", "2"];
+        assert!(parse(&values, "2", true).is_err());
+        assert_eq!(
+            parse(&values, "5", false)?,
+            BamlValue::Enum("Category".into(), "5".into())
+        );
+        // Ordinal and name agree.
+        assert_eq!(
+            parse(&["1", "2"], "2", true)?,
+            BamlValue::Enum("Category".into(), "2".into())
+        );
+        Ok(())
+    }
+
+    fn fuzzy() -> DeserializerOptions {
+        DeserializerOptions {
+            fuzzy_enum_threshold: Some(0.8),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn fuzzy_matches() -> Result<()> {
+        let values = ["RECEIVED", "IN_PROGRESS", "CANCELED"];
+        for (raw, expected) in [
+            ("Recieved", "RECEIVED"),
+            ("in-progress", "IN_PROGRESS"),
+            ("cancelled", "CANCELED"),
+        ] {
+            assert_eq!(
+                parse_with(&values, raw, &fuzzy())?,
+                BamlValue::Enum("Category".into(), expected.into()),
+                "{}",
+                raw
+            );
+        }
+        assert!(parse_with(&values, "shipped", &fuzzy()).is_err());
+
+        // Off unless asked for.
+        assert!(parse(&values, "Recieved", false).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn fuzzy_tie_is_ambiguous() {
+        assert!(parse_with(&["SHIPPED", "SHAPED"], "shiped", &fuzzy()).is_err());
+    }
+
+    #[test]
+    fn fallback() -> Result<()> {
+        let values = ["Billing", "Shipping", "Other"];
+        let options = DeserializerOptions::default();
+
+        let res = parse_with_fallback(&values, Some("Other"), " Warranty claim ", &options)?;
+        assert_eq!(res.enum_fallback_raw(), Some("Warranty claim"));
+        assert_eq!(
+            res.clone().into_value_with_fallback_raw(),
+            BamlValue::Map(
+                [
+                    (
+                        "value".to_string(),
+                        BamlValue::Enum("Category".into(), "Other".into())
+                    ),
+                    (
+                        "raw".to_string(),
+                        BamlValue::String("Warranty claim".into())
+                    ),
+                ]
+                .into_iter()
+                .collect()
+            )
+        );
+        assert_eq!(
+            BamlValue::from(res),
+            BamlValue::Enum("Category".into(), "Other".into())
+        );
+
+        let res = parse_with_fallback(&values, Some("Other"), "shipping", &options)?;
+        assert_eq!(res.enum_fallback_raw(), None);
+        assert_eq!(
+            res.clone().into_value_with_fallback_raw(),
+            BamlValue::Enum("Category".into(), "Shipping".into())
+        );
+        assert_eq!(
+            BamlValue::from(res),
+            BamlValue::Enum("Category".into(), "Shipping".into())
+        );
+
+        assert!(parse_with_fallback(&values, None, "Warranty claim", &options).is_err());
+        for raw in [r#"{"nme": "x"}"#, r#"["x", "y"]"#] {
+            assert!(
+                parse_with_fallback(&values, Some("Other"), raw, &options).is_err(),
+                "{}",
+                raw
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn fallback_in_union_with_class() -> Result<()> {
+        let target = FieldType::Union(vec![
+            FieldType::Enum("Category".into()),
+            FieldType::Class("Pet".into()),
+        ]);
+        let category = Enum {
+            name: Name::new("Category".into()),
+            values: vec![
+                (Name::new("Cat".into()), None),
+                (Name::new("Other".into()), None),
+            ],
+            fallback: Some("Other".into()),
+            synonyms: Default::default(),
+        };
+        let pet = internal_baml_jinja::types::Class {
+            name: Name::new("Pet".into()),
+            fields: vec![
+                (
+                    Name::new("name".into()),
+                    FieldType::Primitive(baml_types::TypeValue::String),
+                    None,
+                ),
+                (
+                    Name::new("age".into()),
+                    FieldType::Primitive(baml_types::TypeValue::Int),
+                    None,
+                ),
+            ],
+            extra_fields: None,
+            discriminator: None,
+        };
+        let of = OutputFormatContent::new(vec![category], vec![pet], target.clone());
+
+        // A misspelled class isn't an enum value nobody thought of.
+        let res = crate::from_str(&of, &target, r#"{"nme": "x"}"#, false);
+        assert!(res.map(BamlValue::from).is_err());
+
+        let res = crate::from_str(&of, &target, "Dog", false)?;
+        assert_eq!(res.enum_fallback_raw(), Some("Dog"));
+        Ok(())
+    }
+
+    #[test]
+    fn synonyms() -> Result<()> {
+        let target = FieldType::Enum("Category".into());
+        let category = Enum {
+            name: Name::new("Category".into()),
+            values: vec![
+                (Name::new("REFUND".into()), None),
+                (Name::new("SHIPPING".into()), None),
+            ],
+            fallback: None,
+            synonyms: [(
+                "REFUND".to_string(),
+                vec!["chargeback".to_string(), "money back".to_string()],
+            )]
+            .into_iter()
+            .collect(),
+        };
+        let of = OutputFormatContent::new(vec![category], vec![], target.clone());
+
+        for raw in ["Chargeback", "they want their money back", "refund"] {
+            assert_eq!(
+                BamlValue::from(crate::from_str(&of, &target, raw, false)?),
+                BamlValue::Enum("Category".into(), "REFUND".into()),
+                "{}",
+                raw
+            );
+        }
+        Ok(())
+    }
+}
@@ -1,6 +1,9 @@
 mod coerce_class;
 mod coerce_enum;
 
+pub(super) use coerce_class::is_tagged_as;
+pub(super) use coerce_enum::match_all as match_all_enum_values;
+
 use anyhow::Result;
 use internal_baml_core::ir::FieldType;
 
@@ -0,0 +1,88 @@
+use std::{collections::HashMap, rc::Rc};
+
+use internal_baml_core::ir::FieldType;
+
+use crate::deserializer::types::BamlValueWithFlags;
+
+use super::{ParsingContext, ParsingError};
+
+/// Addresses of the value, the field type and the target.
+type MemoKey = (
+    *const crate::jsonish::Value,
+    *const FieldType,
+    *const FieldType,
+);
+
+/// The scope the coercion ran in, as it ends up in errors, and its result.
+type MemoEntry = (Vec<String>, Result<BamlValueWithFlags, ParsingError>);
+
+/// Coercions already done during one parse. `Value::AnyOf` candidates and union
+/// options end up coercing the same values to the same types over and over,
+/// which grows exponentially with nested unions.
+///
+/// Entries are found by address: the response, the output format and the
+/// target all outlive the parse, so an address always means the same value or
+/// type. Values made while coercing go through
+/// [`ParsingContext::coerce_temporary`], which keeps them here for the same
+/// reason.
+#[derive(Default)]
+pub(super) struct CoercionCache {
+    // There is rarely more than one entry per key.
+    entries: HashMap<MemoKey, Vec<MemoEntry>>,
+    temporaries: Vec<Rc<crate::jsonish::Value>>,
+}
+
+impl ParsingContext<'_> {
+    /// Returns the result of an earlier identical coercion, or runs `coerce` and
+    /// remembers its result. Disabled by `DeserializerOptions::memoize`.
+    pub(super) fn memoized(
+        &self,
+        field_type: &FieldType,
+        target: &FieldType,
+        value: Option<&crate::jsonish::Value>,
+        coerce: impl FnOnce() -> Result<BamlValueWithFlags, ParsingError>,
+    ) -> Result<BamlValueWithFlags, ParsingError> {
+        // Primitives are cheaper to coerce again than to look up.
+        let Some(value) = value
+            .filter(|_| self.options.memoize && !matches!(field_type, FieldType::Primitive(_)))
+        else {
+            return coerce();
+        };
+
+        let key: MemoKey = (value, field_type, target);
+        let cached = self.cache.borrow().entries.get(&key).and_then(|entries| {
+            entries
+                .iter()
+                .find(|(scope, _)| *scope == self.scope)
+                .map(|(_, result)| result.clone())
+        });
+        if let Some(result) = cached {
+            return result;
+        }
+
+        let result = coerce();
+        self.cache
+            .borrow_mut()
+            .entries
+            .entry(key)
+            .or_default()
+            .push((self.scope.clone(), result.clone()));
+        result
+    }
+
+    /// Coerces a value that isn't part of the response, e.g. a string decoded
+    /// as JSON. With memoization on, it's kept until the end of the parse so
+    /// that its address (and those of the values in it) can't be reused.
+    pub(super) fn coerce_temporary<T>(
+        &self,
+        value: crate::jsonish::Value,
+        coerce: impl FnOnce(&crate::jsonish::Value) -> T,
+    ) -> T {
+        if !self.options.memoize {
+            return coerce(&value);
+        }
+        let value = Rc::new(value);
+        self.cache.borrow_mut().temporaries.push(value.clone());
+        coerce(&value)
+    }
+}
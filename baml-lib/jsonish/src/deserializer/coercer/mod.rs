@@ -5,17 +5,28 @@ mod coerce_primitive;
 mod coerce_union;
 mod field_type;
 mod ir_ref;
+mod memo;
+mod parsing_error;
+use std::{cell::RefCell, rc::Rc};
+
 use anyhow::Result;
 use internal_baml_jinja::types::OutputFormatContent;
 
 use internal_baml_core::ir::FieldType;
 
-use super::types::BamlValueWithFlags;
+use crate::jsonish::{Deadline, LimitExceeded};
+
+use super::{deserialize_options::DeserializerOptions, types::BamlValueWithFlags};
+
+pub use parsing_error::{ParsingError, ParsingErrorKind};
 
 pub struct ParsingContext<'a> {
     scope: Vec<String>,
     of: &'a OutputFormatContent,
+    options: &'a DeserializerOptions,
     allow_partials: bool,
+    cache: Rc<RefCell<memo::CoercionCache>>,
+    deadline: Deadline,
 }
 
 impl ParsingContext<'_> {
@@ -26,11 +37,19 @@ impl ParsingContext<'_> {
         self.scope.join(".")
     }
 
-    pub(crate) fn new<'a>(of: &'a OutputFormatContent, allow_partials: bool) -> ParsingContext<'a> {
+    pub(crate) fn new<'a>(
+        of: &'a OutputFormatContent,
+        options: &'a DeserializerOptions,
+        allow_partials: bool,
+        deadline: Deadline,
+    ) -> ParsingContext<'a> {
         ParsingContext {
             scope: Vec::new(),
             of,
+            options,
             allow_partials,
+            cache: Default::default(),
+            deadline,
         }
     }
 
@@ -40,7 +59,10 @@ impl ParsingContext<'_> {
         ParsingContext {
             scope: new_scope,
             of: self.of,
+            options: self.options,
             allow_partials: self.allow_partials,
+            cache: self.cache.clone(),
+            deadline: self.deadline,
         }
     }
 
@@ -49,167 +71,142 @@ impl ParsingContext<'_> {
         target: &FieldType,
         options: impl IntoIterator<Item = T>,
     ) -> ParsingError {
-        ParsingError {
-            reason: format!(
-                "Too many matches for {}. Got: {}",
-                target,
-                options.into_iter().fold("".to_string(), |acc, f| {
-                    if acc.is_empty() {
-                        return f.to_string();
-                    }
-                    return format!("{}, {}", acc, f);
-                })
-            ),
-            scope: self.scope.clone(),
-        }
+        ParsingError::new(
+            &self.scope,
+            ParsingErrorKind::EnumAmbiguous {
+                expected: target.clone(),
+                matches: options.into_iter().map(|o| o.to_string()).collect(),
+            },
+        )
     }
 
-    pub(crate) fn error_merge_multiple<'a>(
+    pub(crate) fn error_enum_no_match(
         &self,
-        summary: &str,
-        error: impl IntoIterator<Item = &'a ParsingError>,
+        target: &FieldType,
+        got: &crate::jsonish::Value,
     ) -> ParsingError {
-        let reasons = error
-            .into_iter()
-            .map(|e| {
-                // Strip all shared prefixes (assume the same unless different length)
-                let remaining =
-                    e.scope
-                        .iter()
-                        .skip(self.scope.len())
-                        .fold("".to_string(), |acc, f| {
-                            if acc.is_empty() {
-                                return f.clone();
-                            }
-                            return format!("{}.{}", acc, f);
-                        });
-
-                if remaining.is_empty() {
-                    return e.reason.clone();
-                } else {
-                    // Prefix each new lines in e.reason with "  "
-                    return format!("{}: {}", remaining, e.reason.replace("\n", "\n  "));
-                }
-            })
-            .collect::<Vec<_>>();
-
-        ParsingError {
-            reason: format!("{}:\n{}", summary, reasons.join("\n").replace("\n", "\n  ")),
-            scope: self.scope.clone(),
-        }
+        ParsingError::new(
+            &self.scope,
+            ParsingErrorKind::EnumNoMatch {
+                expected: target.clone(),
+            },
+        )
+        .with_raw(got)
+    }
+
+    pub(crate) fn error_no_candidate<'a>(
+        &self,
+        target: &FieldType,
+        errors: impl IntoIterator<Item = &'a ParsingError>,
+    ) -> ParsingError {
+        ParsingError::new(
+            &self.scope,
+            ParsingErrorKind::NoCandidate {
+                expected: target.clone(),
+            },
+        )
+        .with_causes(errors.into_iter().cloned())
     }
 
     pub(crate) fn error_unexpected_empty_array(&self, target: &FieldType) -> ParsingError {
-        ParsingError {
-            reason: format!("Expected {}, got empty array", target.to_string()),
-            scope: self.scope.clone(),
-        }
+        ParsingError::new(
+            &self.scope,
+            ParsingErrorKind::EmptyArray {
+                expected: target.clone(),
+            },
+        )
     }
 
     pub(crate) fn error_unexpected_null(&self, target: &FieldType) -> ParsingError {
-        ParsingError {
-            reason: format!("Expected {}, got null", target),
-            scope: self.scope.clone(),
-        }
+        ParsingError::new(
+            &self.scope,
+            ParsingErrorKind::UnexpectedType {
+                expected: target.clone(),
+                got: "null",
+            },
+        )
+    }
+
+    pub(crate) fn error_duplicate_key(&self, key: &str, count: usize) -> ParsingError {
+        ParsingError::new(
+            &self.scope,
+            ParsingErrorKind::DuplicateKey {
+                key: key.to_string(),
+                count,
+            },
+        )
     }
 
     pub(crate) fn error_image_not_supported(&self) -> ParsingError {
-        ParsingError {
-            reason: "Image type is not supported here".to_string(),
-            scope: self.scope.clone(),
-        }
+        ParsingError::new(&self.scope, ParsingErrorKind::ImageNotSupported)
     }
 
-    pub(crate) fn error_missing_required_field<T: AsRef<str>>(
+    pub(crate) fn error_missing_required_field<'a>(
         &self,
-        unparsed_fields: &[(T, T)],
-        missing_fields: &[T],
+        unparsed_fields: impl IntoIterator<Item = &'a ParsingError>,
+        missing_fields: &[String],
     ) -> ParsingError {
-        let fields = missing_fields
-            .iter()
-            .map(|c| c.as_ref())
-            .collect::<Vec<_>>()
-            .join(", ");
-        let missing_error = match missing_fields.len() {
-            0 => None,
-            1 => Some(format!("Missing required field: {}", fields)),
-            _ => Some(format!("Missing required fields: {}", fields)),
-        };
-
-        let unparsed = unparsed_fields
-            .iter()
-            .map(|(k, v)| format!("{}: {}", k.as_ref(), v.as_ref().replace("\n", "\n  ")))
-            .collect::<Vec<_>>()
-            .join("\n");
-        let unparsed_error = match unparsed_fields.len() {
-            0 => None,
-            1 => Some(format!(
-                "Unparsed field: {}\n  {}",
-                unparsed_fields[0].0.as_ref(),
-                unparsed_fields[0].1.as_ref().replace("\n", "\n  ")
-            )),
-            _ => Some(format!(
-                "Unparsed fields:\n{}\n  {}",
-                unparsed_fields
-                    .iter()
-                    .map(|(k, _)| k.as_ref())
-                    .collect::<Vec<_>>()
-                    .join(", "),
-                unparsed.replace("\n", "\n  ")
-            )),
-        };
-
-        ParsingError {
-            reason: match (missing_error, unparsed_error) {
-                (Some(m), Some(u)) => format!("{}\n{}", m, u),
-                (Some(m), None) => m,
-                (None, Some(u)) => u,
-                (None, None) => "Unexpected error".to_string(),
+        ParsingError::new(
+            &self.scope,
+            ParsingErrorKind::MissingFields {
+                fields: missing_fields.to_vec(),
             },
-            scope: self.scope.clone(),
-        }
+        )
+        .with_causes(unparsed_fields.into_iter().cloned())
     }
 
-    pub(crate) fn error_unexpected_type<T: std::fmt::Display + std::fmt::Debug>(
+    pub(crate) fn error_unexpected_type(
         &self,
         target: &FieldType,
-        got: &T,
+        got: &crate::jsonish::Value,
     ) -> ParsingError {
-        ParsingError {
-            reason: format!("Expected {}, got {}.\n{:#?}", target, got, got),
-            scope: self.scope.clone(),
-        }
+        ParsingError::new(
+            &self.scope,
+            ParsingErrorKind::UnexpectedType {
+                expected: target.clone(),
+                got: parsing_error::json_type(got),
+            },
+        )
+        .with_raw(got)
+    }
+
+    pub(crate) fn error_limit_exceeded(&self, limit: &LimitExceeded) -> ParsingError {
+        ParsingError::new(
+            &self.scope,
+            ParsingErrorKind::LimitExceeded {
+                message: limit.to_string(),
+            },
+        )
     }
 
     pub(crate) fn error_internal<T: std::fmt::Display>(&self, error: T) -> ParsingError {
-        ParsingError {
-            reason: format!("Internal error: {}", error),
-            scope: self.scope.clone(),
-        }
+        ParsingError::new(
+            &self.scope,
+            ParsingErrorKind::Internal {
+                message: error.to_string(),
+            },
+        )
     }
 }
 
-#[derive(Debug, Clone)]
-pub struct ParsingError {
-    reason: String,
-    scope: Vec<String>,
+/// What [`crate::from_str`] fails with when the response doesn't coerce to
+/// the target.
+#[derive(Debug)]
+pub struct CoercionError(pub(crate) ParsingError);
+
+impl CoercionError {
+    pub fn parsing_error(&self) -> &ParsingError {
+        &self.0
+    }
 }
 
-impl std::fmt::Display for ParsingError {
+impl std::fmt::Display for CoercionError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        if self.scope.is_empty() {
-            return write!(f, "Error parsing '<root>': {}", self.reason);
-        }
-        write!(
-            f,
-            "Error parsing '{}': {}",
-            self.scope.join("."),
-            self.reason
-        )
+        write!(f, "Failed to coerce value: {}", self.0)
     }
 }
 
-impl std::error::Error for ParsingError {}
+impl std::error::Error for CoercionError {}
 
 pub trait TypeCoercer {
     fn coerce(
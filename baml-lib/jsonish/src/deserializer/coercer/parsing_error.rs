@@ -0,0 +1,364 @@
+use internal_baml_core::ir::FieldType;
+use serde::Serialize;
+
+/// Why a value didn't coerce to its target, with the errors of what it was
+/// made of, e.g. its fields or the options of a union, as `causes`.
+#[derive(Debug, Clone, Serialize)]
+pub struct ParsingError {
+    // Boxed, it's in the `Err` of every coercion.
+    #[serde(flatten)]
+    pub(super) kind: Box<ParsingErrorKind>,
+    #[serde(rename = "path", serialize_with = "json_pointer")]
+    pub(super) scope: Vec<String>,
+    pub(super) raw: Option<String>,
+    pub(super) causes: Vec<ParsingError>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ParsingErrorKind {
+    /// Required fields of a class that the response didn't have. Those it had
+    /// but that failed are in `causes`.
+    MissingFields {
+        fields: Vec<String>,
+    },
+    /// `got` is the JSON type of the value, or `null` if there was none.
+    UnexpectedType {
+        #[serde(serialize_with = "display")]
+        expected: FieldType,
+        got: &'static str,
+    },
+    EnumNoMatch {
+        #[serde(serialize_with = "display")]
+        expected: FieldType,
+    },
+    EnumAmbiguous {
+        #[serde(serialize_with = "display")]
+        expected: FieldType,
+        matches: Vec<String>,
+    },
+    EmptyArray {
+        #[serde(serialize_with = "display")]
+        expected: FieldType,
+    },
+    /// None of the ways to read the value worked, each one is in `causes`.
+    NoCandidate {
+        #[serde(serialize_with = "display")]
+        expected: FieldType,
+    },
+    DuplicateKey {
+        key: String,
+        count: usize,
+    },
+    ImageNotSupported,
+    LimitExceeded {
+        message: String,
+    },
+    Internal {
+        message: String,
+    },
+}
+
+impl ParsingError {
+    pub(super) fn new(scope: &[String], kind: ParsingErrorKind) -> Self {
+        Self {
+            kind: Box::new(kind),
+            scope: scope.to_vec(),
+            raw: None,
+            causes: vec![],
+        }
+    }
+
+    pub(super) fn with_raw(mut self, value: &crate::jsonish::Value) -> Self {
+        self.raw = Some(match serde_json::Value::from(value) {
+            serde_json::Value::String(s) => s,
+            v => v.to_string(),
+        });
+        self
+    }
+
+    pub(super) fn with_causes(mut self, causes: impl IntoIterator<Item = ParsingError>) -> Self {
+        self.causes = causes.into_iter().collect();
+        self
+    }
+
+    pub fn kind(&self) -> &ParsingErrorKind {
+        &self.kind
+    }
+
+    /// JSON pointer to the value in the output, e.g. `/items/0/name`. Empty
+    /// for the root.
+    pub fn path(&self) -> String {
+        self.scope
+            .iter()
+            .map(|s| format!("/{}", pointer_segment(s)))
+            .collect()
+    }
+
+    /// The part of the response that was being coerced, if the error is about
+    /// a value. Strings are as is, anything else is JSON.
+    pub fn raw(&self) -> Option<&str> {
+        self.raw.as_deref()
+    }
+
+    pub fn causes(&self) -> &[ParsingError] {
+        &self.causes
+    }
+
+    pub(crate) fn scope(&self) -> &[String] {
+        &self.scope
+    }
+
+    /// What went wrong, without where.
+    pub fn reason(&self) -> String {
+        match self.kind.as_ref() {
+            ParsingErrorKind::MissingFields { fields } => self.missing_fields_reason(fields),
+            ParsingErrorKind::UnexpectedType { expected, got } => match &self.raw {
+                Some(raw) => format!("Expected {}, got {}: {}", expected, got, raw),
+                None => format!("Expected {}, got {}", expected, got),
+            },
+            ParsingErrorKind::EnumNoMatch { expected } => format!(
+                "No value of {} matches {}",
+                expected,
+                self.raw.as_deref().unwrap_or_default()
+            ),
+            ParsingErrorKind::EnumAmbiguous { expected, matches } => format!(
+                "Too many matches for {}. Got: {}",
+                expected,
+                matches.join(", ")
+            ),
+            ParsingErrorKind::EmptyArray { expected } => {
+                format!("Expected {}, got empty array", expected)
+            }
+            ParsingErrorKind::NoCandidate { expected } => {
+                let reasons = self
+                    .causes
+                    .iter()
+                    .map(|e| {
+                        // Strip all shared prefixes (assume the same unless different length)
+                        let remaining = e.scope[self.scope.len().min(e.scope.len())..].join(".");
+                        if remaining.is_empty() {
+                            e.reason()
+                        } else {
+                            // Prefix each new lines in e.reason with "  "
+                            format!("{}: {}", remaining, e.reason().replace("\n", "\n  "))
+                        }
+                    })
+                    .collect::<Vec<_>>();
+                format!(
+                    "Failed to find any {} in {} items:\n{}",
+                    expected,
+                    self.causes.len(),
+                    reasons.join("\n").replace("\n", "\n  ")
+                )
+            }
+            ParsingErrorKind::DuplicateKey { key, count } => {
+                format!("Key {} appears {} times", key, count)
+            }
+            ParsingErrorKind::ImageNotSupported => "Image type is not supported here".to_string(),
+            ParsingErrorKind::LimitExceeded { message } => message.clone(),
+            ParsingErrorKind::Internal { message } => format!("Internal error: {}", message),
+        }
+    }
+
+    fn missing_fields_reason(&self, missing_fields: &[String]) -> String {
+        let fields = missing_fields.join(", ");
+        let missing_error = match missing_fields.len() {
+            0 => None,
+            1 => Some(format!("Missing required field: {}", fields)),
+            _ => Some(format!("Missing required fields: {}", fields)),
+        };
+
+        // Each cause is a field, right below this class.
+        let unparsed_fields = self
+            .causes
+            .iter()
+            .map(|e| {
+                let field = e.scope.get(self.scope.len()).map_or("", |s| {
+                    s.strip_prefix("<implied:")
+                        .and_then(|s| s.strip_suffix('>'))
+                        .unwrap_or(s)
+                });
+                (field, e.to_string())
+            })
+            .collect::<Vec<_>>();
+        let unparsed = unparsed_fields
+            .iter()
+            .map(|(k, v)| format!("{}: {}", k, v.replace("\n", "\n  ")))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let unparsed_error = match unparsed_fields.len() {
+            0 => None,
+            1 => Some(format!(
+                "Unparsed field: {}\n  {}",
+                unparsed_fields[0].0,
+                unparsed_fields[0].1.replace("\n", "\n  ")
+            )),
+            _ => Some(format!(
+                "Unparsed fields:\n{}\n  {}",
+                unparsed_fields
+                    .iter()
+                    .map(|(k, _)| *k)
+                    .collect::<Vec<_>>()
+                    .join(", "),
+                unparsed.replace("\n", "\n  ")
+            )),
+        };
+
+        match (missing_error, unparsed_error) {
+            (Some(m), Some(u)) => format!("{}\n{}", m, u),
+            (Some(m), None) => m,
+            (None, Some(u)) => u,
+            (None, None) => "Unexpected error".to_string(),
+        }
+    }
+}
+
+impl std::fmt::Display for ParsingError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.scope.is_empty() {
+            return write!(f, "Error parsing '<root>': {}", self.reason());
+        }
+        write!(
+            f,
+            "Error parsing '{}': {}",
+            self.scope.join("."),
+            self.reason()
+        )
+    }
+}
+
+impl std::error::Error for ParsingError {}
+
+/// The JSON type of `value`, as in [`ParsingErrorKind::UnexpectedType`].
+pub(super) fn json_type(value: &crate::jsonish::Value) -> &'static str {
+    match serde_json::Value::from(value) {
+        serde_json::Value::Null => "null",
+        serde_json::Value::Bool(_) => "boolean",
+        serde_json::Value::Number(_) => "number",
+        serde_json::Value::String(_) => "string",
+        serde_json::Value::Array(_) => "array",
+        serde_json::Value::Object(_) => "object",
+    }
+}
+
+/// A scope segment as a JSON pointer token. A value used as the only field of
+/// a class, or as a list of one, ends up at that field or the first item.
+fn pointer_segment(segment: &str) -> String {
+    let segment = match segment {
+        "<implied>" => "0",
+        s => s
+            .strip_prefix("<implied:")
+            .and_then(|s| s.strip_suffix('>'))
+            .unwrap_or(s),
+    };
+    segment.replace('~', "~0").replace('/', "~1")
+}
+
+fn json_pointer<S: serde::Serializer>(scope: &[String], serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_str(
+        &scope
+            .iter()
+            .map(|s| format!("/{}", pointer_segment(s)))
+            .collect::<String>(),
+    )
+}
+
+fn display<S: serde::Serializer>(
+    value: &impl std::fmt::Display,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.collect_str(value)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use baml_types::TypeValue;
+    use internal_baml_jinja::types::{Class, Name, OutputFormatContent};
+    use serde_json::json;
+    use test_log::test;
+
+    fn error(raw: &str) -> ParsingError {
+        let item = Class {
+            name: Name::new("Item".into()),
+            fields: vec![
+                (
+                    Name::new("name".into()),
+                    FieldType::Primitive(TypeValue::String),
+                    None,
+                ),
+                (
+                    Name::new("qty".into()),
+                    FieldType::Primitive(TypeValue::Int),
+                    None,
+                ),
+            ],
+            extra_fields: None,
+            discriminator: None,
+        };
+        let order = Class {
+            name: Name::new("Order".into()),
+            fields: vec![(
+                Name::new("item".into()),
+                FieldType::Class("Item".into()),
+                None,
+            )],
+            extra_fields: None,
+            discriminator: None,
+        };
+        let of =
+            OutputFormatContent::new(vec![], vec![item, order], FieldType::Class("Order".into()));
+        let error = crate::from_str(&of, of.target(), raw, false).expect_err("should fail");
+        error
+            .downcast::<crate::CoercionError>()
+            .expect("should be a coercion error")
+            .parsing_error()
+            .clone()
+    }
+
+    #[test]
+    fn serializes_as_a_tree() {
+        let error = error(r#"{"item": {"name": "pen", "qty": "some"}}"#);
+        assert_eq!(error.path(), "");
+        assert!(
+            matches!(error.kind(), ParsingErrorKind::MissingFields { fields } if fields.is_empty())
+        );
+
+        let qty = error.causes()[0].causes()[0].clone();
+        assert_eq!(qty.path(), "/item/qty");
+        assert_eq!(qty.raw(), Some("some"));
+        assert_eq!(
+            serde_json::to_value(&qty).unwrap(),
+            json!({
+                "kind": "unexpected_type",
+                "expected": "int",
+                "got": "string",
+                "path": "/item/qty",
+                "raw": "some",
+                "causes": [],
+            })
+        );
+        assert!(error
+            .to_string()
+            .starts_with("Error parsing '<root>': Unparsed field: item\n"));
+    }
+
+    #[test]
+    fn missing_fields_are_listed() {
+        let error = error(r#"{"item": {"qty": 2}}"#);
+        let json = serde_json::to_value(&error).unwrap();
+        assert_eq!(json["kind"], "missing_fields");
+        let item = &json["causes"][0];
+        assert_eq!(item["path"], "/item");
+        assert_eq!(item["kind"], "missing_fields");
+        assert_eq!(item["fields"], json!(["name"]));
+    }
+
+    #[test]
+    fn pointer_segments_are_escaped() {
+        assert_eq!(pointer_segment("a/b~c"), "a~1b~0c");
+        assert_eq!(pointer_segment("<implied:name>"), "name");
+        assert_eq!(pointer_segment("<implied>"), "0");
+    }
+}
@@ -1,3 +1,5 @@
+use std::sync::Arc;
+
 use super::{coercer::ParsingError, types::BamlValueWithFlags};
 
 #[derive(Debug, Clone)]
@@ -5,27 +7,44 @@ pub enum Flag {
     // SingleFromMultiple,
     ObjectFromMarkdown(i32),
     ObjectFromFixedJson(Vec<crate::jsonish::Fixes>),
+    // The response ended before the value was closed.
+    Truncated(crate::jsonish::OpenCollection),
 
     DefaultButHadUnparseableValue(ParsingError),
-    ObjectToString(crate::jsonish::Value),
-    ObjectToPrimitive(crate::jsonish::Value),
-    ExtraKey(String, crate::jsonish::Value),
+    // Flags about converting a value don't copy it: it's found through the
+    // span of the result.
+    ObjectToString,
+    ObjectToPrimitive,
+    // The span of the dropped value, if it came from the response.
+    ExtraKey(String, Option<crate::jsonish::Span>),
     StrippedNonAlphaNumeric(String),
     SubstringMatch(String),
     SingleToArray,
+    EnumsFromText(String),
     ArrayItemParseError(usize, ParsingError),
 
-    JsonToString(crate::jsonish::Value),
+    JsonToString,
+    ParsedStringAsJson,
     ImpliedKey(String),
+    KeyNormalized(String, String),
+    DuplicateKey(String, usize),
 
-    // Values here are all the possible matches.
-    FirstMatch(usize, Vec<Result<BamlValueWithFlags, ParsingError>>),
+    // Index of the picked match. All the possible matches are only kept with
+    // `DeserializerOptions::keep_alternatives`.
+    FirstMatch(
+        usize,
+        Option<Arc<[Result<BamlValueWithFlags, ParsingError>]>>,
+    ),
 
     EnumOneFromMany(Vec<(usize, String)>),
+    EnumFromOrdinal(usize),
+    FuzzyMatch(usize),
+    EnumFallback(String),
 
     DefaultFromNoValue,
-    DefaultButHadValue(crate::jsonish::Value),
+    DefaultButHadValue,
     OptionalDefaultFromNoValue,
+    OmittedMissingField(String),
 
     // String -> X convertions.
     StringToBool(String),
@@ -36,12 +55,16 @@ pub enum Flag {
     FloatToInt(f64),
 
     // X -> Object convertions.
-    NoFields(Option<crate::jsonish::Value>),
+    NoFields,
 }
 
 #[derive(Clone)]
 pub struct DeserializerConditions {
     pub(super) flags: Vec<Flag>,
+    // Where in the raw response the value was parsed from, and the key it was
+    // found under.
+    pub(super) span: Option<crate::jsonish::Span>,
+    pub(super) key_span: Option<crate::jsonish::Span>,
 }
 
 impl std::fmt::Debug for DeserializerConditions {
@@ -65,6 +88,45 @@ impl std::fmt::Display for DeserializerConditions {
     }
 }
 
+impl Flag {
+    /// The variant name, used to look up weights in a `ScoringPolicy`.
+    pub(super) fn name(&self) -> &'static str {
+        match self {
+            Flag::ObjectFromMarkdown(..) => "ObjectFromMarkdown",
+            Flag::ObjectFromFixedJson(..) => "ObjectFromFixedJson",
+            Flag::Truncated(..) => "Truncated",
+            Flag::DefaultButHadUnparseableValue(..) => "DefaultButHadUnparseableValue",
+            Flag::ObjectToString => "ObjectToString",
+            Flag::ObjectToPrimitive => "ObjectToPrimitive",
+            Flag::ExtraKey(..) => "ExtraKey",
+            Flag::StrippedNonAlphaNumeric(..) => "StrippedNonAlphaNumeric",
+            Flag::SubstringMatch(..) => "SubstringMatch",
+            Flag::SingleToArray => "SingleToArray",
+            Flag::EnumsFromText(..) => "EnumsFromText",
+            Flag::ArrayItemParseError(..) => "ArrayItemParseError",
+            Flag::JsonToString => "JsonToString",
+            Flag::ParsedStringAsJson => "ParsedStringAsJson",
+            Flag::ImpliedKey(..) => "ImpliedKey",
+            Flag::KeyNormalized(..) => "KeyNormalized",
+            Flag::DuplicateKey(..) => "DuplicateKey",
+            Flag::FirstMatch(..) => "FirstMatch",
+            Flag::EnumOneFromMany(..) => "EnumOneFromMany",
+            Flag::EnumFromOrdinal(..) => "EnumFromOrdinal",
+            Flag::FuzzyMatch(..) => "FuzzyMatch",
+            Flag::EnumFallback(..) => "EnumFallback",
+            Flag::DefaultFromNoValue => "DefaultFromNoValue",
+            Flag::DefaultButHadValue => "DefaultButHadValue",
+            Flag::OptionalDefaultFromNoValue => "OptionalDefaultFromNoValue",
+            Flag::OmittedMissingField(..) => "OmittedMissingField",
+            Flag::StringToBool(..) => "StringToBool",
+            Flag::StringToNull(..) => "StringToNull",
+            Flag::StringToChar(..) => "StringToChar",
+            Flag::FloatToInt(..) => "FloatToInt",
+            Flag::NoFields => "NoFields",
+        }
+    }
+}
+
 impl std::fmt::Display for Flag {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -74,18 +136,32 @@ impl std::fmt::Display for Flag {
             Flag::DefaultFromNoValue => {
                 write!(f, "Default value")?;
             }
+            Flag::OmittedMissingField(field) => {
+                write!(f, "Omitted missing field: {}", field)?;
+            }
             Flag::ObjectFromFixedJson(fixes) => {
                 write!(f, "JSON (Fixed {} mistakes)", fixes.len())?;
             }
             Flag::ObjectFromMarkdown(_) => {
                 write!(f, "Object from markdown")?;
             }
+            Flag::Truncated(open) => {
+                write!(f, "Truncated inside {:?}", open)?;
+            }
             Flag::ImpliedKey(key) => {
                 write!(f, "Implied key: {}", key)?;
             }
-            Flag::JsonToString(value) => {
-                write!(f, "Json to string: ")?;
-                writeln!(f, "{:#?}", value)?;
+            Flag::KeyNormalized(from, to) => {
+                write!(f, "Key normalized: {} -> {}", from, to)?;
+            }
+            Flag::DuplicateKey(key, count) => {
+                write!(f, "Key {} appeared {} times", key, count)?;
+            }
+            Flag::ParsedStringAsJson => {
+                write!(f, "Parsed string as JSON")?;
+            }
+            Flag::JsonToString => {
+                write!(f, "Json to string")?;
             }
             Flag::ArrayItemParseError(idx, error) => {
                 write!(f, "Error parsing item {}: {}", idx, error)?;
@@ -93,11 +169,14 @@ impl std::fmt::Display for Flag {
             Flag::SingleToArray => {
                 write!(f, "Converted a single value to an array")?;
             }
-            Flag::ExtraKey(key, value) => {
+            Flag::EnumsFromText(value) => {
+                write!(f, "Split text into enum values: {}", value)?;
+            }
+            Flag::ExtraKey(key, span) => {
                 write!(f, "Extra key: {}", key)?;
-                writeln!(f, "----RAW----")?;
-                writeln!(f, "{:#?}", value)?;
-                writeln!(f, "-----------")?;
+                if let Some(span) = span {
+                    write!(f, " at {:?}", span)?;
+                }
             }
             Flag::EnumOneFromMany(values) => {
                 write!(f, "Enum one from many: ")?;
@@ -105,19 +184,23 @@ impl std::fmt::Display for Flag {
                     writeln!(f, "Item {}: {}", idx, value)?;
                 }
             }
+            Flag::EnumFromOrdinal(idx) => {
+                write!(f, "Enum value from ordinal: {}", idx)?;
+            }
+            Flag::FuzzyMatch(distance) => {
+                write!(f, "Fuzzy match (edit distance {})", distance)?;
+            }
             Flag::DefaultButHadUnparseableValue(value) => {
                 write!(f, "Null but had unparseable value")?;
                 writeln!(f, "----RAW----")?;
                 writeln!(f, "{}", value)?;
                 writeln!(f, "-----------")?;
             }
-            Flag::ObjectToString(value) => {
-                write!(f, "Object to string: ")?;
-                writeln!(f, "{:#?}", value)?;
+            Flag::ObjectToString => {
+                write!(f, "Object to string")?;
             }
-            Flag::ObjectToPrimitive(value) => {
-                write!(f, "Object to field: ")?;
-                writeln!(f, "{:#?}", value)?;
+            Flag::ObjectToPrimitive => {
+                write!(f, "Object to field")?;
             }
             Flag::StrippedNonAlphaNumeric(value) => {
                 write!(f, "Stripped non-alphanumeric characters: {}", value)?;
@@ -127,19 +210,21 @@ impl std::fmt::Display for Flag {
             }
             Flag::FirstMatch(idx, values) => {
                 writeln!(f, "Picked item {}:", idx)?;
-                for (idx, value) in values.iter().enumerate() {
+                for (idx, value) in values.iter().flat_map(|v| v.iter()).enumerate() {
                     if let Ok(value) = value {
                         writeln!(f, "{idx}: {:#?}", value)?;
                     }
                 }
             }
-            Flag::DefaultButHadValue(value) => {
-                write!(f, "Null but had value: ")?;
-                writeln!(f, "{:#?}", value)?;
+            Flag::DefaultButHadValue => {
+                write!(f, "Null but had value")?;
             }
             Flag::StringToBool(value) => {
                 write!(f, "String to bool: {}", value)?;
             }
+            Flag::EnumFallback(raw) => {
+                write!(f, "Enum fallback for: {}", raw)?;
+            }
             Flag::StringToNull(value) => {
                 write!(f, "String to null: {}", value)?;
             }
@@ -149,13 +234,8 @@ impl std::fmt::Display for Flag {
             Flag::FloatToInt(value) => {
                 write!(f, "Float to int: {}", value)?;
             }
-            Flag::NoFields(value) => {
-                write!(f, "No fields: ")?;
-                if let Some(value) = value {
-                    writeln!(f, "{:#?}", value)?;
-                } else {
-                    writeln!(f, "<empty>")?;
-                }
+            Flag::NoFields => {
+                write!(f, "No fields")?;
             }
         }
         Ok(())
@@ -173,7 +253,26 @@ impl DeserializerConditions {
     }
 
     pub fn new() -> Self {
-        Self { flags: Vec::new() }
+        Self {
+            flags: Vec::new(),
+            span: None,
+            key_span: None,
+        }
+    }
+
+    /// Records where the value came from, unless a span closer to the value
+    /// was already recorded.
+    pub(super) fn set_span(
+        &mut self,
+        span: &crate::jsonish::Span,
+        key: &Option<crate::jsonish::Span>,
+    ) {
+        if self.span.is_none() {
+            self.span = Some(span.clone());
+        }
+        if self.key_span.is_none() {
+            self.key_span = key.clone();
+        }
     }
 }
 
@@ -0,0 +1,89 @@
+use crate::jsonish::{ExtractionOptions, ParseLimits};
+
+use super::score::ScoringPolicy;
+
+/// Options for [`crate::from_str_with_options`]. The defaults match [`crate::from_str`].
+#[derive(Debug, Clone)]
+pub struct DeserializerOptions {
+    /// What to discard from the raw response before looking for JSON in it.
+    pub extraction: ExtractionOptions,
+    /// Accept ordinal answers for enums ("2", "#2", "option 2"), counting from 1.
+    /// Use with `number_enum_values` when rendering the output format.
+    pub enum_ordinals: bool,
+    /// Minimum similarity (0.0 to 1.0) for matching an enum value by edit
+    /// distance, ignoring case and separators, e.g. `Some(0.8)`. Disabled by
+    /// default.
+    pub fuzzy_enum_threshold: Option<f64>,
+    /// Same as `fuzzy_enum_threshold`, for object keys that don't match any
+    /// field even after normalizing case and separators. Disabled by default.
+    pub fuzzy_key_threshold: Option<f64>,
+    /// Which value to use when an object repeats a key.
+    pub duplicate_keys: DuplicateKeyPolicy,
+    /// Leave optional fields the response never mentioned out of the class,
+    /// instead of setting them to null. An explicit `null` is always kept.
+    pub omit_missing_optional_fields: bool,
+    /// Return enums set to their `@@fallback` value along with the text that
+    /// matched nothing, as [`crate::BamlValueWithFlags::into_value_with_fallback_raw`]
+    /// does. Parsing is the same either way, only the conversion of the result
+    /// changes.
+    pub enum_fallback_raw: bool,
+    /// Strings that mean "no value" when the target is optional or nullable,
+    /// compared case-insensitively and ignoring surrounding whitespace and a
+    /// trailing period, e.g. "n/a" or "not mentioned". None by default, as
+    /// words like "none" or "unknown" are often real answers.
+    pub null_sentinels: Vec<String>,
+    /// Weights used to pick between candidate parses.
+    pub scoring: ScoringPolicy,
+    /// Reuse the result when the same value is coerced to the same type at the
+    /// same path more than once, e.g. for each option of nested unions.
+    pub memoize: bool,
+    /// Keep every candidate in `Flag::FirstMatch`, not just the index of the
+    /// picked one. Only useful for debugging, as each union and `AnyOf` level
+    /// then holds a copy of all its alternatives.
+    pub keep_alternatives: bool,
+    /// Bounds on the work done for one response. Going over them is an error.
+    pub limits: ParseLimits,
+}
+
+/// How a key that appears more than once in an object is resolved. Either way
+/// a `Flag::DuplicateKey` is recorded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DuplicateKeyPolicy {
+    FirstWins,
+    /// Same as `JSON.parse`, and what we did before this was configurable.
+    #[default]
+    LastWins,
+    /// Concatenate the values (and any arrays among them) if the field is a list,
+    /// otherwise last wins.
+    MergeIntoList,
+    Error,
+}
+
+impl Default for DeserializerOptions {
+    fn default() -> Self {
+        Self {
+            extraction: ExtractionOptions::default(),
+            enum_ordinals: false,
+            fuzzy_enum_threshold: None,
+            fuzzy_key_threshold: None,
+            duplicate_keys: DuplicateKeyPolicy::default(),
+            omit_missing_optional_fields: false,
+            enum_fallback_raw: false,
+            null_sentinels: vec![],
+            scoring: ScoringPolicy::default(),
+            memoize: true,
+            keep_alternatives: false,
+            limits: ParseLimits::default(),
+        }
+    }
+}
+
+impl DeserializerOptions {
+    pub(crate) fn is_null_sentinel(&self, value: &str) -> bool {
+        let value = value.trim();
+        let value = value.strip_suffix('.').unwrap_or(value).trim();
+        self.null_sentinels
+            .iter()
+            .any(|s| s.trim().eq_ignore_ascii_case(value))
+    }
+}
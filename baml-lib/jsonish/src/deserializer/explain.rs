@@ -0,0 +1,300 @@
+use super::{
+    coercer::ParsingError,
+    deserialize_flags::Flag,
+    score::{FlagScore, ScoringPolicy},
+    types::BamlValueWithFlags,
+};
+
+/// How a response was coerced: every choice made between candidates, e.g.
+/// the options of a union or the JSON objects found in the response, and why
+/// each candidate lost. See [`crate::explain_from_str`].
+#[derive(Debug, Clone)]
+pub struct Explanation {
+    pub value: BamlValueWithFlags,
+    /// The choices made for the value and those inside it, outermost first.
+    /// Those made inside a candidate are in its own `picks`.
+    pub picks: Vec<Pick>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Pick {
+    /// Field names and list indices of the value that was picked, as in the
+    /// scope of a `ParsingError`.
+    pub path: Vec<String>,
+    pub candidates: Vec<Candidate>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Candidate {
+    pub outcome: Outcome,
+    /// The choices made inside this candidate.
+    pub picks: Vec<Pick>,
+}
+
+#[derive(Debug, Clone)]
+pub enum Outcome {
+    /// Lower scores win, ties go to the first candidate. `breakdown` is the
+    /// flags the score adds up.
+    Picked {
+        score: i32,
+        breakdown: Vec<FlagScore>,
+    },
+    /// Coerced, but scored worse than the picked candidate, e.g. because
+    /// required values had to be filled in with defaults.
+    Lost {
+        score: i32,
+        breakdown: Vec<FlagScore>,
+    },
+    Failed(ParsingError),
+}
+
+impl Explanation {
+    pub(crate) fn new(value: BamlValueWithFlags, policy: &ScoringPolicy) -> Self {
+        let mut picks = vec![];
+        collect_picks(&value, &mut vec![], policy, &mut picks);
+        Self { value, picks }
+    }
+}
+
+/// Picks in `value` and the values inside it, not counting those inside
+/// candidates that were picked over.
+fn collect_picks(
+    value: &BamlValueWithFlags,
+    path: &mut Vec<String>,
+    policy: &ScoringPolicy,
+    out: &mut Vec<Pick>,
+) {
+    // Picks made further out come later, and the candidates of the outermost
+    // one hold those made inside them.
+    let outermost = value.conditions().flags.iter().rev().find_map(|f| match f {
+        Flag::FirstMatch(picked, Some(candidates)) => Some((*picked, candidates)),
+        _ => None,
+    });
+    if let Some((picked, candidates)) = outermost {
+        let candidates = candidates
+            .iter()
+            .enumerate()
+            .map(|(i, c)| candidate(c, i == picked, path, policy))
+            .collect();
+        out.push(Pick {
+            path: path.clone(),
+            candidates,
+        });
+        return;
+    }
+
+    match value {
+        BamlValueWithFlags::List(_, items) => {
+            for (i, item) in items.iter().enumerate() {
+                path.push(i.to_string());
+                collect_picks(item, path, policy, out);
+                path.pop();
+            }
+        }
+        BamlValueWithFlags::Map(_, kv) => {
+            for (k, (_, v)) in kv {
+                path.push(k.clone());
+                collect_picks(v, path, policy, out);
+                path.pop();
+            }
+        }
+        BamlValueWithFlags::Class(_, _, kv) => {
+            for (k, v) in kv {
+                path.push(k.clone());
+                collect_picks(v, path, policy, out);
+                path.pop();
+            }
+        }
+        _ => {}
+    }
+}
+
+fn candidate(
+    candidate: &Result<BamlValueWithFlags, ParsingError>,
+    picked: bool,
+    path: &mut Vec<String>,
+    policy: &ScoringPolicy,
+) -> Candidate {
+    let value = match candidate {
+        Ok(value) => value,
+        Err(e) => {
+            return Candidate {
+                outcome: Outcome::Failed(e.clone()),
+                picks: vec![],
+            }
+        }
+    };
+
+    let mut picks = vec![];
+    collect_picks(value, path, policy, &mut picks);
+    let (score, breakdown) = (value.score_with(policy), policy.breakdown(value));
+    Candidate {
+        outcome: if picked {
+            Outcome::Picked { score, breakdown }
+        } else {
+            Outcome::Lost { score, breakdown }
+        },
+        picks,
+    }
+}
+
+impl std::fmt::Display for Explanation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.picks.is_empty() {
+            return writeln!(f, "Nothing to pick from");
+        }
+        for pick in &self.picks {
+            write_pick(f, pick, 0)?;
+        }
+        Ok(())
+    }
+}
+
+fn write_pick(f: &mut std::fmt::Formatter<'_>, pick: &Pick, depth: usize) -> std::fmt::Result {
+    let indent = "  ".repeat(depth);
+    let at = match pick.path.as_slice() {
+        [] => "<root>".to_string(),
+        path => path.join("."),
+    };
+    writeln!(
+        f,
+        "{}Picked at '{}' from {} candidates:",
+        indent,
+        at,
+        pick.candidates.len()
+    )?;
+    for (i, candidate) in pick.candidates.iter().enumerate() {
+        let (verdict, breakdown) = match &candidate.outcome {
+            Outcome::Picked { score, breakdown } => (format!("picked, score {}", score), breakdown),
+            Outcome::Lost { score, breakdown } => (format!("lost, score {}", score), breakdown),
+            Outcome::Failed(e) => {
+                let reason = e.to_string().replace('\n', &format!("\n{}    ", indent));
+                writeln!(f, "{}  {}: failed, {}", indent, i, reason)?;
+                continue;
+            }
+        };
+        let flags = breakdown
+            .iter()
+            .filter(|s| s.score != 0)
+            .map(|s| match s.path.as_slice() {
+                [] => format!("{} {:+}", s.flag, s.score),
+                path => format!("{} {:+} at {}", s.flag, s.score, path.join(".")),
+            })
+            .collect::<Vec<_>>();
+        if flags.is_empty() {
+            writeln!(f, "{}  {}: {}", indent, i, verdict)?;
+        } else {
+            writeln!(f, "{}  {}: {} ({})", indent, i, verdict, flags.join(", "))?;
+        }
+        for pick in &candidate.picks {
+            write_pick(f, pick, depth + 2)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::DeserializerOptions;
+    use baml_types::{BamlValue, FieldType, TypeValue};
+    use internal_baml_jinja::types::{Class, Name, OutputFormatContent};
+    use test_log::test;
+
+    fn explain(target: &FieldType, raw: &str) -> anyhow::Result<Explanation> {
+        let string = FieldType::Primitive(TypeValue::String);
+        let class = |name: &str, fields: &[&str]| Class {
+            name: Name::new(name.into()),
+            fields: fields
+                .iter()
+                .map(|f| (Name::new(f.to_string()), string.clone(), None))
+                .collect(),
+            extra_fields: None,
+            discriminator: None,
+        };
+        let of = OutputFormatContent::new(
+            vec![],
+            vec![class("Person", &["name"]), class("Pet", &["name", "owner"])],
+            target.clone(),
+        );
+        crate::explain_from_str(&of, target, raw, false, &DeserializerOptions::default())
+    }
+
+    #[test]
+    fn union_branches_say_why_they_lost() -> anyhow::Result<()> {
+        let target = FieldType::Union(vec![
+            FieldType::Class("Pet".into()),
+            FieldType::Class("Person".into()),
+            FieldType::Primitive(TypeValue::Int),
+        ]);
+        let res = explain(&target, r#"{"name": "Ann"}"#)?;
+        assert_eq!(
+            BamlValue::from(&res.value),
+            BamlValue::Class(
+                "Person".into(),
+                [("name".to_string(), BamlValue::String("Ann".into()))]
+                    .into_iter()
+                    .collect()
+            )
+        );
+
+        let [pick] = res.picks.as_slice() else {
+            panic!("Expected one pick, got {}", res);
+        };
+        assert!(pick.path.is_empty());
+        let outcomes = pick
+            .candidates
+            .iter()
+            .map(|c| &c.outcome)
+            .collect::<Vec<_>>();
+        let [Outcome::Failed(pet), Outcome::Picked { score: 0, .. }, Outcome::Failed(int)] =
+            outcomes.as_slice()
+        else {
+            panic!("Unexpected outcomes: {}", res);
+        };
+        assert!(
+            matches!(pet.kind(), crate::ParsingErrorKind::MissingFields { fields } if fields == &["owner"])
+        );
+        assert!(matches!(
+            int.kind(),
+            crate::ParsingErrorKind::UnexpectedType { got: "object", .. }
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn losing_candidates_have_a_score_breakdown() -> anyhow::Result<()> {
+        let string = FieldType::Primitive(TypeValue::String);
+        let target = FieldType::Union(vec![string, FieldType::Class("Person".into())]);
+        let res = explain(&target, r#"{"name": "Ann"}"#)?;
+
+        let [pick] = res.picks.as_slice() else {
+            panic!("Expected one pick, got {}", res);
+        };
+        let Outcome::Lost { score, breakdown } = &pick.candidates[0].outcome else {
+            panic!("Expected the string to lose: {}", res);
+        };
+        assert_eq!(breakdown.iter().map(|s| s.score).sum::<i32>(), *score);
+        assert!(breakdown.iter().any(|s| s.flag == "JsonToString"));
+        assert!(res
+            .to_string()
+            .starts_with("Picked at '<root>' from 2 candidates:\n  0: lost, score 2 (JsonToString +2)\n  1: picked, score 0\n"));
+        Ok(())
+    }
+
+    #[test]
+    fn picks_inside_values_are_found() -> anyhow::Result<()> {
+        let target = FieldType::List(Box::new(FieldType::Union(vec![
+            FieldType::Primitive(TypeValue::Int),
+            FieldType::Primitive(TypeValue::Bool),
+        ])));
+        let res = explain(&target, "[1, true]")?;
+        let paths = res
+            .picks
+            .iter()
+            .map(|p| p.path.join("."))
+            .collect::<Vec<_>>();
+        assert_eq!(paths, vec!["0", "1"]);
+        Ok(())
+    }
+}
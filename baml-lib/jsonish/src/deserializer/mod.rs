@@ -1,5 +1,9 @@
+pub mod alternatives;
 pub mod coercer;
 mod deserialize_flags;
+pub mod deserialize_options;
+pub mod explain;
+pub mod repair;
 // pub mod schema;
-mod score;
+pub mod score;
 pub mod types;
@@ -0,0 +1,392 @@
+use internal_baml_core::ir::{FieldType, TypeValue};
+use internal_baml_jinja::types::{Name, OutputFormatContent};
+
+use super::coercer::{CoercionError, ParsingError, ParsingErrorKind};
+
+/// Something in a response that kept it from coercing, at the path of the
+/// value it is about: field names and list indices, as in the scope of a
+/// `ParsingError`.
+#[derive(Debug, Clone)]
+enum Problem {
+    MissingField {
+        path: Vec<String>,
+    },
+    UnexpectedType {
+        path: Vec<String>,
+        expected: FieldType,
+        got: String,
+    },
+    TooManyMatches {
+        path: Vec<String>,
+        expected: FieldType,
+    },
+    DuplicateKey {
+        path: Vec<String>,
+        count: usize,
+    },
+}
+
+impl Problem {
+    fn path(&self) -> &[String] {
+        match self {
+            Problem::MissingField { path }
+            | Problem::UnexpectedType { path, .. }
+            | Problem::TooManyMatches { path, .. }
+            | Problem::DuplicateKey { path, .. } => path,
+        }
+    }
+}
+
+/// A message for the model that wrote `raw_string`, saying what to change so
+/// that it parses as the target of `of`. `error` is what
+/// [`crate::from_str`] failed with. Meant to be sent as the next user turn
+/// before asking again.
+pub fn repair_prompt(error: &anyhow::Error, raw_string: &str, of: &OutputFormatContent) -> String {
+    const ASK: &str =
+        "Reply with the complete corrected output, in the requested format and nothing else.";
+
+    if raw_string.trim().is_empty() {
+        return format!("Your response was empty. {}", ASK);
+    }
+
+    let lines = match error.downcast_ref::<CoercionError>() {
+        Some(CoercionError(e)) => {
+            let mut problems = vec![];
+            collect_problems(e, &mut problems);
+            describe(&problems, of)
+        }
+        None => vec![],
+    };
+    if lines.is_empty() {
+        // Nothing to point at, e.g. there was no JSON in the response.
+        let reason = error.to_string();
+        let reason = reason.lines().next().unwrap_or_default();
+        return format!("Your response could not be parsed: {}. {}", reason, ASK);
+    }
+
+    let mut message = "Your response did not match the requested format:\n".to_string();
+    for line in lines {
+        message.push_str(&format!("- {}\n", line));
+    }
+    message.push_str(ASK);
+    message
+}
+
+/// The problems in `error` and its causes, in the order they were found.
+fn collect_problems(error: &ParsingError, out: &mut Vec<Problem>) {
+    let path = error.scope().to_vec();
+    let got = || error.raw().unwrap_or("null").to_string();
+    match error.kind() {
+        ParsingErrorKind::MissingFields { fields } => {
+            out.extend(fields.iter().map(|field| Problem::MissingField {
+                path: path.iter().chain([field]).cloned().collect(),
+            }));
+        }
+        ParsingErrorKind::UnexpectedType { expected, .. }
+        | ParsingErrorKind::EnumNoMatch { expected } => out.push(Problem::UnexpectedType {
+            path,
+            expected: expected.clone(),
+            got: got(),
+        }),
+        ParsingErrorKind::EmptyArray { expected } => out.push(Problem::UnexpectedType {
+            path,
+            expected: expected.clone(),
+            got: "[]".to_string(),
+        }),
+        ParsingErrorKind::EnumAmbiguous { expected, .. } => out.push(Problem::TooManyMatches {
+            path,
+            expected: expected.clone(),
+        }),
+        ParsingErrorKind::DuplicateKey { count, .. } => out.push(Problem::DuplicateKey {
+            path,
+            count: *count,
+        }),
+        ParsingErrorKind::NoCandidate { .. }
+        | ParsingErrorKind::ImageNotSupported
+        | ParsingErrorKind::LimitExceeded { .. }
+        | ParsingErrorKind::Internal { .. } => {}
+    }
+    for cause in error.causes() {
+        collect_problems(cause, out);
+    }
+}
+
+fn describe(problems: &[Problem], of: &OutputFormatContent) -> Vec<String> {
+    // Every option of a union, and every way of reading the response, is
+    // tried. Only say a value has the wrong type if none got further into it.
+    let got_further = |path: &[String]| {
+        problems.iter().any(|p| {
+            p.path().starts_with(path)
+                && (p.path().len() > path.len() || !matches!(p, Problem::UnexpectedType { .. }))
+        })
+    };
+
+    let mut lines: Vec<String> = vec![];
+    let mut mistyped: Vec<&[String]> = vec![];
+    for problem in problems {
+        let location = match problem.path() {
+            [] => "The response".to_string(),
+            path => format!("`{}`", render_path(path, of)),
+        };
+        let line = match problem {
+            Problem::MissingField { .. } => format!("{}: required field is missing", location),
+            Problem::UnexpectedType { path, got, .. } => {
+                if got_further(path) || mistyped.contains(&path.as_slice()) {
+                    continue;
+                }
+                mistyped.push(path);
+                // Everything it could have been, e.g. each option of a union.
+                let expected = problems
+                    .iter()
+                    .filter_map(|p| match p {
+                        Problem::UnexpectedType {
+                            path: other,
+                            expected,
+                            ..
+                        } if other == path => Some(describe_type(expected, of)),
+                        _ => None,
+                    })
+                    .fold(Vec::<String>::new(), |mut acc, e| {
+                        if !acc.contains(&e) {
+                            acc.push(e);
+                        }
+                        acc
+                    });
+                format!(
+                    "{}: expected {}, got \"{}\"",
+                    location,
+                    expected.join(" or "),
+                    excerpt(got)
+                )
+            }
+            Problem::TooManyMatches { expected, .. } => format!(
+                "{}: matches more than one value, use exactly {}",
+                location,
+                describe_type(expected, of)
+            ),
+            Problem::DuplicateKey { count, .. } => {
+                format!("{}: appears {} times, give it once", location, count)
+            }
+        };
+        if !lines.contains(&line) {
+            lines.push(line);
+        }
+    }
+    lines
+}
+
+fn describe_type(t: &FieldType, of: &OutputFormatContent) -> String {
+    match t {
+        FieldType::Primitive(TypeValue::String) => "a string".into(),
+        FieldType::Primitive(TypeValue::Int) => "an integer".into(),
+        FieldType::Primitive(TypeValue::Float) => "a number".into(),
+        FieldType::Primitive(TypeValue::Bool) => "a boolean".into(),
+        FieldType::Primitive(TypeValue::Null) => "null".into(),
+        FieldType::Primitive(TypeValue::Image) => "an image".into(),
+        FieldType::Enum(name) => match of.find_enum(name) {
+            Ok(e) => format!(
+                "one of: {}",
+                e.values
+                    .iter()
+                    .map(|(name, _)| name.rendered_name())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            Err(_) => name.clone(),
+        },
+        FieldType::Class(_) | FieldType::Map(..) => "an object".into(),
+        FieldType::List(_) | FieldType::Tuple(_) => "a list".into(),
+        FieldType::Optional(t) => format!("{} or null", describe_type(t, of)),
+        FieldType::Union(options) => options
+            .iter()
+            .map(|t| describe_type(t, of))
+            .collect::<Vec<_>>()
+            .join(" or "),
+    }
+}
+
+/// `got` on one line, and short enough to quote.
+fn excerpt(got: &str) -> String {
+    const MAX_CHARS: usize = 60;
+    let got = got.split_whitespace().collect::<Vec<_>>().join(" ");
+    match got.char_indices().nth(MAX_CHARS) {
+        Some((end, _)) => format!("{}...", &got[..end]),
+        None => got,
+    }
+}
+
+/// `path` with the field names the model was shown, e.g. `items[0].name`.
+fn render_path(path: &[String], of: &OutputFormatContent) -> String {
+    let mut rendered = String::new();
+    let mut current = Some(of.target());
+    for segment in path {
+        // A single value that was used as a list of one.
+        if segment == "<implied>" {
+            current = current.and_then(list_item);
+            continue;
+        }
+        if let (Ok(_), Some(item)) = (segment.parse::<usize>(), current.and_then(list_item)) {
+            rendered.push_str(&format!("[{}]", segment));
+            current = Some(item);
+            continue;
+        }
+
+        // A value that was used as the only field of a class.
+        let name = segment
+            .strip_prefix("<implied:")
+            .and_then(|s| s.strip_suffix('>'))
+            .unwrap_or(segment);
+        let field = current.and_then(|t| find_field(of, t, name));
+        if !rendered.is_empty() {
+            rendered.push('.');
+        }
+        rendered.push_str(field.map_or(name, |(name, _)| name.rendered_name()));
+        current = field.map(|(_, t)| t);
+    }
+    rendered
+}
+
+fn list_item(t: &FieldType) -> Option<&FieldType> {
+    match t {
+        FieldType::List(item) => Some(item),
+        FieldType::Optional(t) => list_item(t),
+        FieldType::Union(options) => options.iter().find_map(list_item),
+        _ => None,
+    }
+}
+
+fn find_field<'a>(
+    of: &'a OutputFormatContent,
+    t: &'a FieldType,
+    name: &str,
+) -> Option<(&'a Name, &'a FieldType)> {
+    match t {
+        FieldType::Class(class) => of
+            .find_class(class)
+            .ok()?
+            .fields
+            .iter()
+            .find(|(field, ..)| field.real_name() == name)
+            .map(|(field, t, _)| (field, t)),
+        FieldType::Optional(t) => find_field(of, t, name),
+        FieldType::Union(options) => options.iter().find_map(|t| find_field(of, t, name)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use internal_baml_jinja::types::{Class, Enum};
+    use test_log::test;
+
+    fn of() -> OutputFormatContent {
+        let string = FieldType::Primitive(TypeValue::String);
+        let address = Class {
+            name: Name::new("Address".into()),
+            fields: vec![(Name::new("city".into()), string.clone(), None)],
+            extra_fields: None,
+            discriminator: None,
+        };
+        let person = Class {
+            name: Name::new("Person".into()),
+            fields: vec![
+                (
+                    Name::new_with_alias("name".into(), Some("full_name".into())),
+                    string,
+                    None,
+                ),
+                (
+                    Name::new("age".into()),
+                    FieldType::Primitive(TypeValue::Int),
+                    None,
+                ),
+                (
+                    Name::new("homes".into()),
+                    FieldType::List(Box::new(FieldType::Class("Address".into()))),
+                    None,
+                ),
+                (
+                    Name::new("color".into()),
+                    FieldType::Enum("Color".into()),
+                    None,
+                ),
+            ],
+            extra_fields: None,
+            discriminator: None,
+        };
+        let color = Enum {
+            name: Name::new("Color".into()),
+            values: vec![
+                (Name::new("RED".into()), None),
+                (Name::new("GREEN".into()), None),
+            ],
+            fallback: None,
+            synonyms: Default::default(),
+        };
+        OutputFormatContent::new(
+            vec![color],
+            vec![address, person],
+            FieldType::Class("Person".into()),
+        )
+    }
+
+    fn prompt(raw: &str) -> String {
+        let of = of();
+        let error = crate::from_str(&of, of.target(), raw, false).expect_err("should fail");
+        repair_prompt(&error, raw, &of)
+    }
+
+    #[test]
+    fn lists_problems_by_rendered_path() {
+        let raw = r#"{"full_name": "Ann", "age": "twenty", "homes": [], "color": "blue"}"#;
+        assert_eq!(
+            prompt(raw),
+            "Your response did not match the requested format:\n\
+             - `age`: expected an integer, got \"twenty\"\n\
+             - `color`: expected one of: RED, GREEN, got \"blue\"\n\
+             Reply with the complete corrected output, in the requested format and nothing else."
+        );
+
+        let raw = r#"{"homes": [{"town": "Rome"}, "x"], "color": "RED GREEN"}"#;
+        let prompt = prompt(raw);
+        for line in [
+            "- `full_name`: required field is missing\n",
+            "- `age`: required field is missing\n",
+            "- `color`: matches more than one value, use exactly one of: RED, GREEN\n",
+        ] {
+            assert!(prompt.contains(line), "{line} in {prompt}");
+        }
+    }
+
+    #[test]
+    fn union_options_are_listed_together() {
+        let of = of();
+        let target = FieldType::Union(vec![
+            FieldType::Primitive(TypeValue::Int),
+            FieldType::Primitive(TypeValue::Bool),
+        ]);
+        let error = crate::from_str(&of, &target, "many", false).expect_err("should fail");
+        assert!(repair_prompt(&error, "many", &of)
+            .contains("- The response: expected an integer or a boolean, got \"many\"\n"));
+    }
+
+    #[test]
+    fn other_failures_are_summarized() {
+        let of = of();
+        assert!(prompt("  ").starts_with("Your response was empty. "));
+
+        let options = crate::DeserializerOptions {
+            limits: crate::ParseLimits {
+                max_input_bytes: 4,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let raw = r#"{"full_name": "Ann"}"#;
+        let error = crate::from_str_with_options(&of, of.target(), raw, false, &options)
+            .expect_err("should fail");
+        assert!(repair_prompt(&error, raw, &of)
+            .starts_with("Your response could not be parsed: Response is longer than 4 bytes. "));
+    }
+}
@@ -1,6 +1,8 @@
+use std::collections::HashMap;
+
 use super::{
     deserialize_flags::{DeserializerConditions, Flag},
-    types::{BamlValueWithFlags, ValueWithFlags},
+    types::BamlValueWithFlags,
 };
 
 // Lower is better
@@ -8,23 +10,133 @@ pub trait WithScore {
     fn score(&self) -> i32;
 }
 
-impl WithScore for BamlValueWithFlags {
-    fn score(&self) -> i32 {
-        match self {
-            BamlValueWithFlags::String(s) => s.score(),
-            BamlValueWithFlags::Int(s) => s.score(),
-            BamlValueWithFlags::Float(s) => s.score(),
-            BamlValueWithFlags::Bool(s) => s.score(),
-            BamlValueWithFlags::List(s, items) => {
-                s.score() + 10 * items.iter().map(WithScore::score).sum::<i32>()
+/// Weights used to pick the best of several candidate parses. Lower scores win,
+/// and the defaults are what [`crate::from_str`] uses.
+#[derive(Debug, Clone)]
+pub struct ScoringPolicy {
+    /// Replaces the weight of a flag, keyed by its name (e.g. `"ImpliedKey"` or
+    /// `"ObjectFromMarkdown"`). Negative weights make a flag preferable.
+    pub flag_weights: HashMap<String, i32>,
+    /// Applied to the sum of the scores of a list's items.
+    pub list_item_multiplier: i32,
+    /// Applied to the sum of the scores of a class's fields.
+    pub class_field_multiplier: i32,
+}
+
+impl Default for ScoringPolicy {
+    fn default() -> Self {
+        Self {
+            flag_weights: HashMap::new(),
+            list_item_multiplier: 1,
+            class_field_multiplier: 1,
+        }
+    }
+}
+
+/// One flag's part of a score: the flag's name, the path of the value it is
+/// on, and its weight after any list or class multipliers.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FlagScore {
+    pub path: Vec<String>,
+    pub flag: &'static str,
+    pub score: i32,
+}
+
+impl ScoringPolicy {
+    pub(super) fn flag_score(&self, flag: &Flag) -> i32 {
+        self.flag_weights
+            .get(flag.name())
+            .copied()
+            .unwrap_or_else(|| flag.score())
+    }
+
+    pub(super) fn conditions_score(&self, conditions: &DeserializerConditions) -> i32 {
+        conditions.flags.iter().map(|f| self.flag_score(f)).sum()
+    }
+
+    pub(super) fn value_score(&self, value: &BamlValueWithFlags) -> i32 {
+        match value {
+            BamlValueWithFlags::String(v) => self.conditions_score(&v.flags),
+            BamlValueWithFlags::Int(v) => self.conditions_score(&v.flags),
+            BamlValueWithFlags::Float(v) => self.conditions_score(&v.flags),
+            BamlValueWithFlags::Bool(v) => self.conditions_score(&v.flags),
+            BamlValueWithFlags::List(f, items) => {
+                self.conditions_score(f)
+                    + self.list_item_multiplier
+                        * items.iter().map(|i| self.value_score(i)).sum::<i32>()
             }
-            BamlValueWithFlags::Map(s, _) => s.score(),
-            BamlValueWithFlags::Enum(_, s) => s.score(),
-            BamlValueWithFlags::Class(_, s, kv) => {
-                s.score() + 10 * kv.iter().map(|(_, v)| v.score()).sum::<i32>()
+            BamlValueWithFlags::Map(f, kv) => {
+                self.conditions_score(f)
+                    + kv.iter()
+                        .map(|(_, (f, v))| self.conditions_score(f) + self.value_score(v))
+                        .sum::<i32>()
             }
-            BamlValueWithFlags::Null(s) => s.score(),
-            BamlValueWithFlags::Image(s) => s.score(),
+            BamlValueWithFlags::Enum(_, v) => self.conditions_score(&v.flags),
+            BamlValueWithFlags::Class(_, f, kv) => {
+                self.conditions_score(f)
+                    + self.class_field_multiplier
+                        * kv.iter().map(|(_, v)| self.value_score(v)).sum::<i32>()
+            }
+            BamlValueWithFlags::Null(f) => self.conditions_score(f),
+            BamlValueWithFlags::Image(v) => self.conditions_score(&v.flags),
+        }
+    }
+}
+
+impl ScoringPolicy {
+    /// The flags `value_score` adds up for `value`, in the same order.
+    pub(super) fn breakdown(&self, value: &BamlValueWithFlags) -> Vec<FlagScore> {
+        let mut out = vec![];
+        self.add_breakdown(value, &mut vec![], 1, &mut out);
+        out
+    }
+
+    fn add_conditions_breakdown(
+        &self,
+        conditions: &DeserializerConditions,
+        path: &[String],
+        multiplier: i32,
+        out: &mut Vec<FlagScore>,
+    ) {
+        out.extend(conditions.flags.iter().map(|f| FlagScore {
+            path: path.to_vec(),
+            flag: f.name(),
+            score: multiplier * self.flag_score(f),
+        }));
+    }
+
+    fn add_breakdown(
+        &self,
+        value: &BamlValueWithFlags,
+        path: &mut Vec<String>,
+        multiplier: i32,
+        out: &mut Vec<FlagScore>,
+    ) {
+        self.add_conditions_breakdown(value.conditions(), path, multiplier, out);
+        match value {
+            BamlValueWithFlags::List(_, items) => {
+                for (i, item) in items.iter().enumerate() {
+                    path.push(i.to_string());
+                    self.add_breakdown(item, path, multiplier * self.list_item_multiplier, out);
+                    path.pop();
+                }
+            }
+            BamlValueWithFlags::Map(_, kv) => {
+                for (k, (f, v)) in kv {
+                    path.push(k.clone());
+                    self.add_conditions_breakdown(f, path, multiplier, out);
+                    self.add_breakdown(v, path, multiplier, out);
+                    path.pop();
+                }
+            }
+            BamlValueWithFlags::Class(_, _, kv) => {
+                for (k, v) in kv {
+                    path.push(k.clone());
+                    self.add_breakdown(v, path, multiplier * self.class_field_multiplier, out);
+                    path.pop();
+                }
+            }
+            _ => {}
         }
     }
 }
@@ -34,40 +146,91 @@ impl WithScore for Flag {
         match self {
             Flag::OptionalDefaultFromNoValue => 1,
             Flag::DefaultFromNoValue => 100,
-            Flag::DefaultButHadValue(_) => 110,
+            Flag::OmittedMissingField(_) => 100,
+            Flag::DefaultButHadValue => 110,
+            Flag::EnumFallback(_) => 110,
             Flag::ObjectFromFixedJson(_) => 0,
+            // Every candidate from a cut off response is, so it's up to the
+            // caller whether that matters.
+            Flag::Truncated(_) => 0,
             Flag::ObjectFromMarkdown(s) => *s,
             Flag::DefaultButHadUnparseableValue(_) => 2,
-            Flag::ObjectToString(_) => 2,
-            Flag::ObjectToPrimitive(_) => 2,
+            Flag::ObjectToString => 2,
+            Flag::ObjectToPrimitive => 2,
             Flag::ExtraKey(_, _) => 1,
             Flag::StrippedNonAlphaNumeric(_) => 3,
             Flag::SubstringMatch(_) => 2,
             Flag::ImpliedKey(_) => 2,
-            Flag::JsonToString(_) => 2,
+            Flag::KeyNormalized(_, _) => 1,
+            Flag::DuplicateKey(_, _) => 1,
+            Flag::JsonToString => 2,
+            // Must cost something so genuine strings win in `string | T` unions.
+            Flag::ParsedStringAsJson => 1,
             Flag::SingleToArray => 1,
+            Flag::EnumsFromText(_) => 1,
             // Parsing errors are bad.
             Flag::ArrayItemParseError(x, _) => 1 + (*x as i32),
             // Harmless to drop additional matches
             Flag::FirstMatch(_, _) => 1,
             Flag::EnumOneFromMany(i) => i.into_iter().map(|(i, _)| *i as i32).sum::<i32>(),
+            Flag::EnumFromOrdinal(_) => 1,
+            Flag::FuzzyMatch(distance) => 2 + *distance as i32,
             Flag::StringToBool(_) => 1,
             Flag::StringToNull(_) => 1,
             Flag::StringToChar(_) => 1,
             Flag::FloatToInt(_) => 1,
-            Flag::NoFields(_) => 1,
+            Flag::NoFields => 1,
         }
     }
 }
 
-impl<T> WithScore for ValueWithFlags<T> {
-    fn score(&self) -> i32 {
-        self.flags.score()
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::DeserializerOptions;
+    use baml_types::{BamlValue, FieldType, TypeValue};
+    use internal_baml_jinja::types::{Class, Name, OutputFormatContent};
+    use test_log::test;
+
+    #[test]
+    fn policy_overrides_flag_weights() -> anyhow::Result<()> {
+        let string = FieldType::Primitive(TypeValue::String);
+        let target = FieldType::Union(vec![FieldType::Class("Person".into()), string.clone()]);
+        let person = Class {
+            name: Name::new("Person".into()),
+            fields: vec![(Name::new("name".into()), string, None)],
+            extra_fields: None,
+            discriminator: None,
+        };
+        let of = OutputFormatContent::new(vec![], vec![person], target.clone());
+        let raw = r#"{"name": "x"}"#;
+
+        let res = crate::from_str(&of, &target, raw, false)?;
+        assert!(matches!(BamlValue::from(res), BamlValue::Class(..)));
+
+        let options = DeserializerOptions {
+            scoring: ScoringPolicy {
+                flag_weights: [("JsonToString".to_string(), -10)].into_iter().collect(),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let res = crate::from_str_with_options(&of, &target, raw, false, &options)?;
+        assert!(matches!(BamlValue::from(res), BamlValue::String(_)));
+        Ok(())
     }
-}
 
-impl WithScore for DeserializerConditions {
-    fn score(&self) -> i32 {
-        self.flags.iter().map(WithScore::score).sum()
+    #[test]
+    fn multipliers_scale_children() {
+        let child = || {
+            BamlValueWithFlags::Null(DeserializerConditions::new().with_flag(Flag::SingleToArray))
+        };
+        let list = BamlValueWithFlags::List(DeserializerConditions::new(), vec![child(), child()]);
+        let policy = ScoringPolicy {
+            list_item_multiplier: 10,
+            ..Default::default()
+        };
+        assert_eq!(list.score(), 2);
+        assert_eq!(list.score_with(&policy), 20);
     }
 }
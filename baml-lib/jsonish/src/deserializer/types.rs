@@ -4,7 +4,7 @@ use baml_types::{BamlImage, BamlMap, BamlValue};
 
 use super::{
     deserialize_flags::{DeserializerConditions, Flag},
-    score::WithScore,
+    score::ScoringPolicy,
 };
 
 // Recursive parity
@@ -31,30 +31,177 @@ pub enum BamlValueWithFlags {
 
 impl BamlValueWithFlags {
     pub fn score(&self) -> i32 {
+        self.score_with(&ScoringPolicy::default())
+    }
+
+    /// Same as `score`, with the weights from `policy`.
+    pub fn score_with(&self, policy: &ScoringPolicy) -> i32 {
+        policy.value_score(self)
+    }
+
+    /// The raw text of an enum that matched no value and was set to the
+    /// enum's `@@fallback` value instead.
+    pub fn enum_fallback_raw(&self) -> Option<&str> {
         match self {
-            BamlValueWithFlags::String(f) => f.score(),
-            BamlValueWithFlags::Int(f) => f.score(),
-            BamlValueWithFlags::Float(f) => f.score(),
-            BamlValueWithFlags::Bool(f) => f.score(),
-            BamlValueWithFlags::List(f, items) => {
-                f.score() + items.iter().map(|i| i.score()).sum::<i32>()
-            }
-            BamlValueWithFlags::Map(f, kv) => {
-                f.score()
-                    + kv.iter()
-                        .map(|(_, (f, v))| f.score() + v.score())
-                        .sum::<i32>()
-            }
-            BamlValueWithFlags::Enum(_, f) => f.score(),
-            BamlValueWithFlags::Class(_, f, items) => {
-                f.score() + items.iter().map(|(_, v)| v.score()).sum::<i32>()
+            BamlValueWithFlags::Enum(_, v) => v.flags.flags.iter().find_map(|f| match f {
+                Flag::EnumFallback(raw) => Some(raw.as_str()),
+                _ => None,
+            }),
+            _ => None,
+        }
+    }
+
+    /// Same as converting to a `BamlValue`, except that enums set to their
+    /// `@@fallback` value become `{"value": ..., "raw": ...}`, with the text
+    /// that matched nothing, so misses can be reviewed.
+    pub fn into_value_with_fallback_raw(self) -> BamlValue {
+        let raw = self.enum_fallback_raw().map(str::to_string);
+        match self {
+            BamlValueWithFlags::Enum(name, v) => match raw {
+                Some(raw) => BamlValue::Map(BamlMap::from_iter([
+                    ("value".into(), BamlValue::Enum(name, v.value)),
+                    ("raw".into(), BamlValue::String(raw)),
+                ])),
+                None => BamlValue::Enum(name, v.value),
+            },
+            BamlValueWithFlags::List(_, items) => BamlValue::List(
+                items
+                    .into_iter()
+                    .map(Self::into_value_with_fallback_raw)
+                    .collect(),
+            ),
+            BamlValueWithFlags::Map(_, kv) => BamlValue::Map(
+                kv.into_iter()
+                    .map(|(k, (_, v))| (k, v.into_value_with_fallback_raw()))
+                    .collect(),
+            ),
+            BamlValueWithFlags::Class(name, _, kv) => BamlValue::Class(
+                name,
+                kv.into_iter()
+                    .map(|(k, v)| (k, v.into_value_with_fallback_raw()))
+                    .collect(),
+            ),
+            v => v.into(),
+        }
+    }
+
+    /// Where each value in the result came from, keyed by its path: field
+    /// names, map keys and list indices from the root, which has an empty path.
+    pub fn sources(&self) -> Vec<(Vec<String>, ValueSource)> {
+        let mut sources = vec![];
+        self.walk(&mut vec![], &mut |path, value| {
+            sources.push((path.to_vec(), value.source()))
+        });
+        sources
+    }
+
+    fn source(&self) -> ValueSource {
+        let conditions = self.conditions();
+        let is_default = conditions.flags.iter().any(|f| {
+            matches!(
+                f,
+                Flag::DefaultFromNoValue
+                    | Flag::OptionalDefaultFromNoValue
+                    | Flag::DefaultButHadValue
+                    | Flag::DefaultButHadUnparseableValue(_)
+            )
+        });
+        match &conditions.span {
+            _ if is_default => ValueSource::Default,
+            Some(span) => ValueSource::Span {
+                value: span.clone(),
+                key: conditions.key_span.clone(),
+            },
+            None => ValueSource::Unknown,
+        }
+    }
+
+    /// Whether the response ended, e.g. at a token limit, before all of it
+    /// was closed. Values cut off inside anything the result dropped still
+    /// leave the root open, so this only reports a shallower path for them.
+    pub fn completion_state(&self) -> CompletionState {
+        let mut state = CompletionState::Complete;
+        self.walk(&mut vec![], &mut |path, value| {
+            let open = value.conditions().flags.iter().find_map(|f| match f {
+                Flag::Truncated(open) => Some(*open),
+                _ => None,
+            });
+            match (&state, open) {
+                (CompletionState::Truncated { path: deepest, .. }, Some(_))
+                    if deepest.len() >= path.len() => {}
+                (_, Some(inside)) => {
+                    state = CompletionState::Truncated {
+                        inside,
+                        path: path.to_vec(),
+                    }
+                }
+                _ => {}
             }
-            BamlValueWithFlags::Null(f) => f.score(),
-            BamlValueWithFlags::Image(f) => f.score(),
+        });
+        state
+    }
+
+    /// Calls `f` with every value and its path, parents first.
+    pub(super) fn walk(&self, path: &mut Vec<String>, f: &mut impl FnMut(&[String], &Self)) {
+        f(path, self);
+
+        let mut visit = |key: String, value: &BamlValueWithFlags| {
+            path.push(key);
+            value.walk(path, f);
+            path.pop();
+        };
+        match self {
+            BamlValueWithFlags::List(_, items) => items
+                .iter()
+                .enumerate()
+                .for_each(|(i, v)| visit(i.to_string(), v)),
+            BamlValueWithFlags::Map(_, kv) => kv.iter().for_each(|(k, (_, v))| visit(k.clone(), v)),
+            BamlValueWithFlags::Class(_, _, kv) => kv.iter().for_each(|(k, v)| visit(k.clone(), v)),
+            _ => {}
+        }
+    }
+
+    /// Whether this is the `null` filled in for an optional field the response
+    /// never mentioned, as opposed to an explicit `null`.
+    pub fn is_missing(&self) -> bool {
+        match self {
+            BamlValueWithFlags::Null(f) => f
+                .flags
+                .iter()
+                .any(|f| matches!(f, Flag::DefaultFromNoValue)),
+            _ => false,
         }
     }
 }
 
+/// Whether a response was cut off before it was complete, see
+/// [`BamlValueWithFlags::completion_state`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CompletionState {
+    Complete,
+    /// Ended inside the deepest open value, at `path` in the result.
+    Truncated {
+        inside: crate::jsonish::OpenCollection,
+        path: Vec<String>,
+    },
+}
+
+/// Where a value in a [`BamlValueWithFlags`] came from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ValueSource {
+    /// Parsed from the `value` byte range of the raw response. `key` is the
+    /// range of its key when it was an object entry.
+    Span {
+        value: crate::jsonish::Span,
+        key: Option<crate::jsonish::Span>,
+    },
+    /// Filled in because the response had no usable value.
+    Default,
+    /// Made up while coercing, e.g. a list from several JSON objects found
+    /// in prose.
+    Unknown,
+}
+
 #[derive(Debug, Clone)]
 pub struct ValueWithFlags<T> {
     value: T,
@@ -147,17 +294,41 @@ impl From<&BamlValueWithFlags> for BamlValue {
 
 impl BamlValueWithFlags {
     pub(super) fn add_flag(&mut self, flag: Flag) {
+        self.conditions_mut().add_flag(flag)
+    }
+
+    pub(crate) fn with_span(mut self, span: crate::jsonish::Span) -> Self {
+        self.conditions_mut().set_span(&span, &None);
+        self
+    }
+
+    pub(super) fn conditions(&self) -> &DeserializerConditions {
+        match self {
+            BamlValueWithFlags::String(v) => &v.flags,
+            BamlValueWithFlags::Int(v) => &v.flags,
+            BamlValueWithFlags::Float(v) => &v.flags,
+            BamlValueWithFlags::Bool(v) => &v.flags,
+            BamlValueWithFlags::List(v, _) => v,
+            BamlValueWithFlags::Map(v, _) => v,
+            BamlValueWithFlags::Enum(_, v) => &v.flags,
+            BamlValueWithFlags::Class(_, v, _) => v,
+            BamlValueWithFlags::Null(v) => v,
+            BamlValueWithFlags::Image(v) => &v.flags,
+        }
+    }
+
+    pub(super) fn conditions_mut(&mut self) -> &mut DeserializerConditions {
         match self {
-            BamlValueWithFlags::String(v) => v.flags.add_flag(flag),
-            BamlValueWithFlags::Int(v) => v.flags.add_flag(flag),
-            BamlValueWithFlags::Float(v) => v.flags.add_flag(flag),
-            BamlValueWithFlags::Bool(v) => v.flags.add_flag(flag),
-            BamlValueWithFlags::List(v, _) => v.add_flag(flag),
-            BamlValueWithFlags::Map(v, _) => v.add_flag(flag),
-            BamlValueWithFlags::Enum(_, v) => v.flags.add_flag(flag),
-            BamlValueWithFlags::Class(_, v, _) => v.add_flag(flag),
-            BamlValueWithFlags::Null(v) => v.add_flag(flag),
-            BamlValueWithFlags::Image(v) => v.flags.add_flag(flag),
+            BamlValueWithFlags::String(v) => &mut v.flags,
+            BamlValueWithFlags::Int(v) => &mut v.flags,
+            BamlValueWithFlags::Float(v) => &mut v.flags,
+            BamlValueWithFlags::Bool(v) => &mut v.flags,
+            BamlValueWithFlags::List(v, _) => v,
+            BamlValueWithFlags::Map(v, _) => v,
+            BamlValueWithFlags::Enum(_, v) => &mut v.flags,
+            BamlValueWithFlags::Class(_, v, _) => v,
+            BamlValueWithFlags::Null(v) => v,
+            BamlValueWithFlags::Image(v) => &mut v.flags,
         }
     }
 
@@ -262,3 +433,97 @@ impl std::fmt::Display for BamlValueWithFlags {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use baml_types::{FieldType, TypeValue};
+    use internal_baml_jinja::types::{Class, Name, OutputFormatContent};
+    use test_log::test;
+
+    fn person() -> (OutputFormatContent, FieldType) {
+        let string = FieldType::Primitive(TypeValue::String);
+        let class = Class {
+            name: Name::new("Person".into()),
+            fields: vec![
+                (Name::new("name".into()), string.clone(), None),
+                (
+                    Name::new("age".into()),
+                    FieldType::Optional(Box::new(FieldType::Primitive(TypeValue::Int))),
+                    None,
+                ),
+                (
+                    Name::new("tags".into()),
+                    FieldType::List(Box::new(string)),
+                    None,
+                ),
+            ],
+            extra_fields: None,
+            discriminator: None,
+        };
+        let target = FieldType::Class("Person".into());
+        (
+            OutputFormatContent::new(vec![], vec![class], target.clone()),
+            target,
+        )
+    }
+
+    #[test]
+    fn sources_point_into_raw_response() -> anyhow::Result<()> {
+        let (of, target) = person();
+        let raw = "<think>{\"name\": \"Bob\"}</think>Sure:\n```json\n{\"name\": \"Ann\", tags: [a, \"b\"]}\n```";
+
+        let options = crate::DeserializerOptions {
+            extraction: crate::ExtractionOptions {
+                strip_tags: vec![("<think>".into(), "</think>".into())],
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let res = crate::from_str_with_options(&of, &target, raw, false, &options)?;
+        let sources = res.sources().into_iter().collect::<BamlMap<_, _>>();
+        let text = |path: &[&str]| match &sources
+            [&path.iter().map(|p| p.to_string()).collect::<Vec<_>>()]
+        {
+            ValueSource::Span { value, key } => (&raw[value.clone()], key.clone().map(|k| &raw[k])),
+            source => panic!("{:?} has no span: {:?}", path, source),
+        };
+
+        assert_eq!(text(&[]), (r#"{"name": "Ann", tags: [a, "b"]}"#, None));
+        assert_eq!(text(&["name"]), (r#""Ann""#, Some(r#""name""#)));
+        assert_eq!(text(&["tags"]), (r#"[a, "b"]"#, Some("tags")));
+        assert_eq!(text(&["tags", "0"]), ("a", None));
+        assert_eq!(text(&["tags", "1"]), (r#""b""#, None));
+        assert_eq!(sources[&vec!["age".to_string()]], ValueSource::Default);
+        Ok(())
+    }
+
+    #[test]
+    fn completion_state_finds_deepest_open_value() -> anyhow::Result<()> {
+        let (of, target) = person();
+        let state = |raw: &str| -> anyhow::Result<_> {
+            Ok(crate::from_str(&of, &target, raw, true)?.completion_state())
+        };
+
+        assert_eq!(
+            state(r#"{"name": "Ann", "tags": ["a"]}"#)?,
+            CompletionState::Complete
+        );
+        assert_eq!(
+            state(r#"{"name": "Ann", "tags": ["a", "b"#)?,
+            CompletionState::Truncated {
+                inside: crate::jsonish::OpenCollection::String,
+                path: vec!["tags".into(), "1".into()],
+            }
+        );
+        // Numbers and the like end with the input, so only the object is open.
+        assert_eq!(
+            state(r#"{"name": "Ann", "age": 4"#)?,
+            CompletionState::Truncated {
+                inside: crate::jsonish::OpenCollection::Object,
+                path: vec![],
+            }
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,51 @@
+//! What the fuzz targets in `fuzz/` and the malformed input tests run against.
+
+use baml_types::{FieldType, TypeValue};
+use internal_baml_jinja::types::{Class, Enum, Name, OutputFormatContent};
+
+/// A few targets that send the input down different coercion paths.
+pub fn schema() -> (OutputFormatContent, Vec<FieldType>) {
+    let string = FieldType::Primitive(TypeValue::String);
+    let item = Class {
+        name: Name::new("Item".into()),
+        fields: vec![
+            (Name::new("name".into()), string.clone(), None),
+            (
+                Name::new("tags".into()),
+                FieldType::List(Box::new(string)),
+                None,
+            ),
+            (
+                Name::new("size".into()),
+                FieldType::Optional(Box::new(FieldType::Primitive(TypeValue::Float))),
+                None,
+            ),
+        ],
+        extra_fields: None,
+        discriminator: Some("kind".into()),
+    };
+    let color = Enum {
+        name: Name::new("Color".into()),
+        values: vec![
+            (Name::new("Red".into()), None),
+            (Name::new("Blue".into()), None),
+        ],
+        fallback: None,
+        synonyms: Default::default(),
+    };
+    let item_type = FieldType::Class("Item".into());
+    let targets = vec![
+        item_type.clone(),
+        FieldType::List(Box::new(item_type.clone())),
+        FieldType::Union(vec![
+            item_type.clone(),
+            FieldType::Enum("Color".into()),
+            FieldType::Primitive(TypeValue::Int),
+        ]),
+        FieldType::Primitive(TypeValue::Bool),
+    ];
+    (
+        OutputFormatContent::new(vec![color], vec![item], item_type),
+        targets,
+    )
+}
@@ -3,7 +3,10 @@ mod parser;
 // mod test_iterative_parser;
 mod value;
 
-pub use value::{Fixes, Value};
+pub use value::{Fixes, OpenCollection, Span, Value};
 
 // pub use iterative_parser::{parse_jsonish_value, JSONishOptions};
-pub use parser::{parse, ParseOptions};
+pub(crate) use parser::Deadline;
+pub use parser::{
+    parse, ExtractionOptions, LimitExceeded, ParseLimits, ParseOptions, StreamingParser,
+};
@@ -1,25 +1,51 @@
 use anyhow::Result;
 
 use crate::jsonish::{
-    parser::{fixing_parser, markdown_parser, multi_json_parser},
+    parser::{extraction, fixing_parser, markdown_parser, multi_json_parser, spans},
     value::Fixes,
     Value,
 };
 
-use super::ParseOptions;
+use super::{LimitExceeded, ParseOptions};
 
-pub fn parse<'a>(str: &'a str, mut options: ParseOptions) -> Result<Value> {
+pub fn parse<'a>(str: &'a str, options: ParseOptions) -> Result<Value> {
+    if options.depth > 0 {
+        return parse_nested(str, options);
+    }
+
+    // Text found inside the response is never longer, and its candidates end
+    // up in the response's.
+    let limits = options.limits.clone();
+    limits.check_input(str)?;
+    let value = parse_nested(str, options)?;
+    limits.check_candidates(&value)?;
+    Ok(value)
+}
+
+fn parse_nested(str: &str, mut options: ParseOptions) -> Result<Value> {
     log::debug!("Parsing:\n{:?}\n-------\n{}\n-------", options, str);
 
     options.depth += 1;
-    if options.depth > 100 {
-        return Err(anyhow::anyhow!(
-            "Depth limit reached. Likely a circular reference."
-        ));
+    options.limits.check_depth(options.depth)?;
+    options.deadline.check()?;
+
+    // Only the raw response can contain reasoning or preambles.
+    if options.depth == 1 {
+        if let Some(extracted) = extraction::extract(str, &options.extraction) {
+            log::debug!("Discarded before parsing: {:?}", extracted.discarded);
+            if extracted.text.is_empty() {
+                // Nothing but discarded content, same as `StreamingParser::value`.
+                return Ok(Value::String(String::new()));
+            }
+            return parse(&extracted.text, options).map(|v| extracted.annotate(v));
+        }
     }
 
     match serde_json::from_str(str) {
-        Ok(v) => return Ok(Value::AnyOf(vec![v], str.to_string())),
+        Ok(v) => {
+            options.limits.check_value(&v, 0)?;
+            return Ok(Value::AnyOf(vec![spans::locate(v, str)], str.to_string()));
+        }
         Err(e) => {
             log::debug!("Invalid JSON: {:?}", e);
         }
@@ -27,38 +53,16 @@ pub fn parse<'a>(str: &'a str, mut options: ParseOptions) -> Result<Value> {
 
     if options.allow_markdown_json {
         match markdown_parser::parse(str, &options) {
-            Ok(items) => match items.len() {
-                0 => {}
-                1 => {
-                    let (str, v) = items.into_iter().next().unwrap();
-                    return Ok(Value::AnyOf(
-                        vec![Value::Markdown(str.to_string(), Box::new(v))],
-                        str.to_string(),
-                    ));
-                }
-                _ => {
-                    // In the case of multiple JSON objects:
-                    // Consider it as:
-                    // [item1, item2, ..., itemN, [item1, item2, ..., itemN], str]
-                    // AKA:
-                    //  - All the items individually
-                    //  - All the items as a list
-                    //  - The original string
-
-                    let items = items
-                        .into_iter()
-                        .map(|(s, v)| Value::Markdown(s.to_string(), Box::new(v)))
-                        .collect::<Vec<_>>();
-                    let array = Value::Array(items.clone());
-                    let items = items
-                        .into_iter()
-                        .chain(std::iter::once(array))
-                        .collect::<Vec<_>>();
-                    return Ok(Value::AnyOf(items, str.to_string()));
+            Ok(items) => {
+                if let Some(v) = from_markdown(items, str) {
+                    return Ok(v);
                 }
-            },
+            }
             Err(e) => {
                 log::debug!("Markdown parsing error: {:?}", e);
+                if e.is::<LimitExceeded>() {
+                    return Err(e);
+                }
             }
         }
     }
@@ -98,6 +102,9 @@ pub fn parse<'a>(str: &'a str, mut options: ParseOptions) -> Result<Value> {
             }
             Err(e) => {
                 log::debug!("Error parsing multiple JSON objects: {:?}", e);
+                if e.is::<LimitExceeded>() {
+                    return Err(e);
+                }
             }
         }
     }
@@ -105,48 +112,83 @@ pub fn parse<'a>(str: &'a str, mut options: ParseOptions) -> Result<Value> {
     if options.allow_fixes {
         match fixing_parser::parse(str, &options) {
             Ok(items) => {
-                match items.len() {
-                    0 => {}
-                    1 => {
-                        let (v, fixes) = items.into_iter().next().unwrap();
-                        return Ok(Value::AnyOf(
-                            vec![Value::FixedJson(v.into(), fixes)],
-                            str.to_string(),
-                        ));
-                    }
-                    _ => {
-                        // In the case of multiple JSON objects:
-                        // Consider it as:
-                        // [item1, item2, ..., itemN, [item1, item2, ..., itemN], str]
-                        // AKA:
-                        //  - All the items individually
-                        //  - All the items as a list
-                        //  - The original string
-
-                        let items = items
-                            .into_iter()
-                            .map(|(v, fixes)| Value::FixedJson(v.into(), fixes))
-                            .collect::<Vec<_>>();
-
-                        let items_clone = Value::Array(items.clone());
-
-                        let items = items
-                            .into_iter()
-                            .chain(std::iter::once(items_clone))
-                            .collect::<Vec<_>>();
-                        return Ok(Value::AnyOf(items, str.to_string()));
-                    }
+                if let Some(v) = from_fixed_json(items, str) {
+                    return Ok(v);
                 }
             }
             Err(e) => {
                 log::debug!("Error fixing json: {:?}", e);
+                if e.is::<LimitExceeded>() {
+                    return Err(e);
+                }
             }
         }
     }
 
     if options.allow_as_string {
-        return Ok(Value::String(str.to_string()));
+        return Ok(Value::String(str.to_string()).spanned(0..str.len()));
     }
 
     Err(anyhow::anyhow!("Failed to parse JSON"))
 }
+
+pub(super) fn from_markdown(items: Vec<(String, Value)>, str: &str) -> Option<Value> {
+    match items.len() {
+        0 => None,
+        1 => {
+            let (str, v) = items.into_iter().next().unwrap();
+            Some(Value::AnyOf(
+                vec![Value::Markdown(str.to_string(), Box::new(v))],
+                str.to_string(),
+            ))
+        }
+        _ => {
+            // In the case of multiple JSON objects:
+            // Consider it as:
+            // [item1, item2, ..., itemN, [item1, item2, ..., itemN], str]
+            // AKA:
+            //  - All the items individually
+            //  - All the items as a list
+            //  - The original string
+
+            let items = items
+                .into_iter()
+                .map(|(s, v)| Value::Markdown(s.to_string(), Box::new(v)))
+                .collect::<Vec<_>>();
+            let array = Value::Array(items.clone());
+            let items = items
+                .into_iter()
+                .chain(std::iter::once(array))
+                .collect::<Vec<_>>();
+            Some(Value::AnyOf(items, str.to_string()))
+        }
+    }
+}
+
+pub(super) fn from_fixed_json(items: Vec<(Value, Vec<Fixes>)>, str: &str) -> Option<Value> {
+    match items.len() {
+        0 => None,
+        1 => {
+            let (v, fixes) = items.into_iter().next().unwrap();
+            Some(Value::AnyOf(
+                vec![Value::FixedJson(v.into(), fixes)],
+                str.to_string(),
+            ))
+        }
+        _ => {
+            // Same as above, all the items, then all of them as a list.
+            let items = items
+                .into_iter()
+                .map(|(v, fixes)| Value::FixedJson(v.into(), fixes))
+                .collect::<Vec<_>>();
+
+            let items_clone = Value::Array(items.clone());
+
+            let items = items
+                .into_iter()
+                .chain(std::iter::once(items_clone))
+                .collect::<Vec<_>>();
+            Some(Value::AnyOf(items, str.to_string()))
+        }
+    }
+}
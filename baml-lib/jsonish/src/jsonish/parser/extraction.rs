@@ -0,0 +1,255 @@
+use crate::jsonish::{value::Fixes, Value};
+
+use super::spans::OffsetMap;
+
+/// Controls which parts of a response are discarded before we try to find
+/// JSON in it. Reasoning models tend to put JSON-looking drafts in their
+/// reasoning, which can otherwise win the candidate scoring.
+#[derive(Debug, Clone, Default)]
+pub struct ExtractionOptions {
+    /// (open, close) tag pairs whose content is removed, e.g. `<think>`, `</think>`.
+    /// An unclosed tag removes everything after it. Tags are found anywhere,
+    /// even inside JSON strings, so there are none by default.
+    pub strip_tags: Vec<(String, String)>,
+    /// If any of these markers (matched case-insensitively) occur, only the
+    /// content after the last one is parsed, e.g. `Answer:`.
+    pub answer_markers: Vec<String>,
+    /// When there are multiple fenced blocks, only parse the last one.
+    pub prefer_last_fenced_block: bool,
+}
+
+pub(super) struct Extracted {
+    pub text: String,
+    pub discarded: Vec<Fixes>,
+    /// Where `text` is in the response.
+    pub offsets: OffsetMap,
+}
+
+impl Extracted {
+    /// Also moves the spans of a value parsed from `text` to the response.
+    pub fn annotate(&self, mut value: Value) -> Value {
+        value.map_spans(&|s| self.offsets.span(s));
+        annotate(value, &self.discarded)
+    }
+}
+
+/// Marks every candidate parsed from the extracted text with what was discarded.
+pub(super) fn annotate(value: Value, discarded: &[Fixes]) -> Value {
+    match value {
+        Value::AnyOf(items, s) => Value::AnyOf(
+            items
+                .into_iter()
+                .map(|v| Value::FixedJson(v.into(), discarded.to_vec()))
+                .collect(),
+            s,
+        ),
+        v => Value::FixedJson(v.into(), discarded.to_vec()),
+    }
+}
+
+/// Returns `None` if nothing was discarded. The text is empty if everything
+/// was, e.g. the response so far is an unclosed `<think>`.
+pub(super) fn extract(str: &str, options: &ExtractionOptions) -> Option<Extracted> {
+    let mut text = str.to_string();
+    let mut discarded = vec![];
+    let mut offsets = OffsetMap::default();
+
+    for (open, close) in &options.strip_tags {
+        if open.is_empty() {
+            continue;
+        }
+        while let Some(start) = text.find(open.as_str()) {
+            let end = match text[start + open.len()..].find(close.as_str()) {
+                Some(idx) if !close.is_empty() => start + open.len() + idx + close.len(),
+                _ => text.len(),
+            };
+            discarded.push(Fixes::StrippedTag(text[start..end].to_string()));
+            text.replace_range(start..end, "");
+            offsets.remove(start..end);
+        }
+    }
+
+    // Markers are ascii, so lowercasing doesn't move any byte offsets.
+    let lowercase = text.to_ascii_lowercase();
+    let marker_end = options
+        .answer_markers
+        .iter()
+        .filter(|m| !m.is_empty())
+        .filter_map(|m| {
+            lowercase
+                .rfind(&m.to_ascii_lowercase())
+                .map(|idx| idx + m.len())
+        })
+        .max();
+    if let Some(end) = marker_end {
+        if !text[end..].trim().is_empty() {
+            discarded.push(Fixes::StrippedPreamble(text[..end].to_string()));
+            text = text[end..].to_string();
+            offsets.remove(0..end);
+        }
+    }
+
+    if options.prefer_last_fenced_block {
+        if let Some((start, end)) = last_fenced_block(&text) {
+            discarded.push(Fixes::StrippedFencedBlocks(text[..start].to_string()));
+            text = text[start..end].to_string();
+            offsets.remove(0..start);
+        }
+    }
+
+    if discarded.is_empty() {
+        return None;
+    }
+
+    offsets.remove(0..text.len() - text.trim_start().len());
+    Some(Extracted {
+        text: text.trim().to_string(),
+        discarded,
+        offsets,
+    })
+}
+
+/// The byte range of the last of at least two fenced blocks.
+fn last_fenced_block(str: &str) -> Option<(usize, usize)> {
+    let md_tag_start = regex::Regex::new(r"```([a-zA-Z0-9 ]*)(?:\n|$)").ok()?;
+    let md_tag_end = regex::Regex::new(r"```(?:\n|$)").ok()?;
+
+    let mut blocks = vec![];
+    let mut offset = 0;
+    while let Some(cap) = md_tag_start.find(&str[offset..]) {
+        let start = offset + cap.start();
+        let content_start = offset + cap.end();
+        match md_tag_end.find(&str[content_start..]) {
+            Some(end) => {
+                offset = content_start + end.end();
+                blocks.push((start, offset));
+            }
+            None => {
+                blocks.push((start, str.len()));
+                break;
+            }
+        }
+    }
+
+    match blocks.len() {
+        0 | 1 => None,
+        _ => blocks.pop(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::jsonish::parser::{entry, ParseOptions};
+    use anyhow::Result;
+    use test_log::test;
+
+    fn think() -> ExtractionOptions {
+        ExtractionOptions {
+            strip_tags: vec![("<think>".into(), "</think>".into())],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn strips_think_tags() {
+        let res = extract(r#"<think>Maybe {"a": 1}?</think>{"a": 2}"#, &think()).unwrap();
+        assert_eq!(res.text, r#"{"a": 2}"#);
+        assert_eq!(
+            res.discarded,
+            vec![Fixes::StrippedTag(
+                r#"<think>Maybe {"a": 1}?</think>"#.into()
+            )]
+        );
+    }
+
+    #[test]
+    fn unclosed_tag_leaves_nothing() {
+        let res = extract(r#"<think>draft: {"a": 1}"#, &think()).unwrap();
+        assert_eq!(res.text, "");
+        assert_eq!(
+            res.discarded,
+            vec![Fixes::StrippedTag(r#"<think>draft: {"a": 1}"#.into())]
+        );
+    }
+
+    #[test]
+    fn unclosed_tag_json_is_not_coerced() {
+        let target = baml_types::FieldType::Primitive(baml_types::TypeValue::Int);
+        let of = crate::OutputFormatContent::new(vec![], vec![], target.clone());
+        let options = crate::DeserializerOptions {
+            extraction: think(),
+            ..Default::default()
+        };
+        let res = crate::from_str_with_options(&of, &target, "<think>draft: 5", false, &options);
+        assert!(res.is_err(), "{:?}", res);
+    }
+
+    #[test]
+    fn tags_are_kept_by_default() -> Result<()> {
+        let string = baml_types::FieldType::Primitive(baml_types::TypeValue::String);
+        let class = internal_baml_jinja::types::Class {
+            name: internal_baml_jinja::types::Name::new("Note".into()),
+            fields: ["a", "b"]
+                .iter()
+                .map(|f| {
+                    (
+                        internal_baml_jinja::types::Name::new(f.to_string()),
+                        string.clone(),
+                        None,
+                    )
+                })
+                .collect(),
+            extra_fields: None,
+            discriminator: None,
+        };
+        let target = baml_types::FieldType::Class("Note".into());
+        let of = crate::OutputFormatContent::new(vec![], vec![class], target.clone());
+        let raw = r#"{"a": "wrap reasoning in <think> tags", "b": "kept"}"#;
+
+        let res = baml_types::BamlValue::from(crate::from_str(&of, &target, raw, false)?);
+        let baml_types::BamlValue::Class(_, fields) = res else {
+            panic!("Expected class, got {:?}", res);
+        };
+        assert_eq!(
+            fields["a"],
+            baml_types::BamlValue::String("wrap reasoning in <think> tags".into())
+        );
+        assert_eq!(fields["b"], baml_types::BamlValue::String("kept".into()));
+        Ok(())
+    }
+
+    #[test]
+    fn answer_marker_and_last_block() {
+        let options = ExtractionOptions {
+            strip_tags: vec![],
+            answer_markers: vec!["Final answer:".into()],
+            prefer_last_fenced_block: true,
+        };
+        let res = extract(
+            "Let me think step by step...\n```json\n{\"a\": 1}\n```\nFINAL ANSWER:\n```json\n{\"a\": 2}\n```\nand\n```json\n{\"a\": 3}\n```",
+            &options,
+        )
+        .unwrap();
+        assert_eq!(res.text, "```json\n{\"a\": 3}\n```");
+        assert_eq!(res.discarded.len(), 2);
+    }
+
+    #[test]
+    fn reasoning_json_is_not_a_candidate() -> Result<()> {
+        let res = entry::parse(
+            r#"<think>draft: {"a": 1}</think> Here you go: {"a": 2}"#,
+            ParseOptions::default().with_extraction(think()),
+        )?;
+        let Value::AnyOf(items, _) = res else {
+            panic!("Expected AnyOf, got {:#?}", res);
+        };
+        assert_eq!(items.len(), 1);
+        let Value::FixedJson(inner, fixes) = &items[0] else {
+            panic!("Expected FixedJson, got {:#?}", items[0]);
+        };
+        assert!(matches!(fixes[0], Fixes::StrippedTag(_)));
+        assert!(inner.to_string().contains("a: 2"));
+        Ok(())
+    }
+}
@@ -3,12 +3,12 @@ mod json_parse_state;
 
 use crate::jsonish::{value::Fixes, Value};
 
-use self::json_parse_state::JsonParseState;
+use self::json_parse_state::{Checkpoint, JsonParseState};
 
 use super::ParseOptions;
 use anyhow::Result;
 
-pub fn parse<'a>(str: &'a str, _options: &ParseOptions) -> Result<Vec<(Value, Vec<Fixes>)>> {
+pub fn parse<'a>(str: &'a str, options: &ParseOptions) -> Result<Vec<(Value, Vec<Fixes>)>> {
     // Try to fix some common JSON issues
     // - Unquoted single word strings
     // - Single quoted strings
@@ -26,28 +26,53 @@ pub fn parse<'a>(str: &'a str, _options: &ParseOptions) -> Result<Vec<(Value, Ve
     // - Unterminated arrays
     // - Unterminated objects
     // - Unterminated strings
+    // - Python literals (True, False, None, tuples, triple-quoted strings)
+    // - JSON5 numbers (hex, NaN, Infinity, explicit + signs)
 
     let mut state = JsonParseState::new();
+    state.process(str, 0, options)?;
+    finish(state)
+}
+
+/// The fixing parser over text that is still growing. Text before the last
+/// checkpoint is never processed again.
+#[derive(Clone, Default)]
+pub struct IncrementalParser {
+    state: JsonParseState,
+    checkpoint: Checkpoint,
+}
 
-    let mut chars = str.char_indices().peekable();
-    while let Some((count, c)) = chars.next() {
-        let peekable = str[count + c.len_utf8()..].char_indices().peekable();
-        match state.process_token(c, peekable) {
-            Ok(increments) => {
-                for _ in 0..increments {
-                    chars.next();
+impl IncrementalParser {
+    /// `str` is all the text so far, and starts with the text seen before.
+    pub fn advance(&mut self, str: &str, options: &ParseOptions) {
+        match self.state.process(str, self.checkpoint.pos, options) {
+            Ok(checkpoint) => {
+                if let Some(checkpoint) = checkpoint {
+                    self.checkpoint = checkpoint;
                 }
+                self.state.restore(&self.checkpoint);
             }
             Err(e) => {
-                return Err(e);
+                log::debug!("Error fixing json, starting over: {:?}", e);
+                *self = Default::default();
             }
         }
     }
 
-    // If we still have a collection open, close it
-    while !state.collection_stack.is_empty() {
-        state.complete_collection();
+    /// What [`parse`] returns for `str`, which starts with the text seen by
+    /// [`Self::advance`]. Only the text after the checkpoint is processed. The
+    /// state is cloned, as the values are moved out of it, which is no more
+    /// work than building them.
+    pub fn parse(&self, str: &str, options: &ParseOptions) -> Result<Vec<(Value, Vec<Fixes>)>> {
+        let mut state = self.state.clone();
+        state.process(str, self.checkpoint.pos, options)?;
+        finish(state)
     }
+}
+
+fn finish(mut state: JsonParseState) -> Result<Vec<(Value, Vec<Fixes>)>> {
+    // If we still have a collection open, close it
+    state.complete_truncated()?;
 
     // Determine what to return.
 
@@ -91,3 +116,160 @@ pub fn parse<'a>(str: &'a str, _options: &ParseOptions) -> Result<Vec<(Value, Ve
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use test_log::test;
+
+    fn parse_one(str: &str) -> Result<(Value, Vec<Fixes>)> {
+        let mut res = parse(str, &ParseOptions::default())?;
+        assert_eq!(res.len(), 1);
+        Ok(res.remove(0))
+    }
+
+    #[test]
+    fn python_constants() -> Result<()> {
+        let (value, fixes) = parse_one(r#"{'a': True, 'b': False, 'c': None}"#)?;
+        assert_eq!(
+            value,
+            Value::Object(
+                [
+                    ("a".to_string(), Value::Boolean(true)),
+                    ("b".to_string(), Value::Boolean(false)),
+                    ("c".to_string(), Value::Null),
+                ]
+                .into_iter()
+                .collect()
+            )
+        );
+        assert!(fixes.contains(&Fixes::PythonConstant("True".into())));
+        assert!(fixes.contains(&Fixes::PythonConstant("None".into())));
+        Ok(())
+    }
+
+    #[test]
+    fn python_tuples() -> Result<()> {
+        let (value, fixes) = parse_one(r#"{"point": (1, (2, "x"))}"#)?;
+        assert_eq!(
+            value,
+            Value::Object(
+                [(
+                    "point".to_string(),
+                    Value::Array(vec![
+                        Value::Number(1.into()),
+                        Value::Array(vec![Value::Number(2.into()), Value::String("x".into())]),
+                    ])
+                )]
+                .into_iter()
+                .collect()
+            )
+        );
+        assert_eq!(
+            fixes.iter().filter(|f| **f == Fixes::TupleToArray).count(),
+            2
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn parenthesized_text_is_not_a_tuple() -> Result<()> {
+        let (value, fixes) = parse_one(r#"{"name": (unknown), "country": "x", "note": (1, "b")}"#)?;
+        assert_eq!(
+            value,
+            Value::Object(
+                [
+                    ("name".to_string(), Value::String("(unknown)".into())),
+                    ("country".to_string(), Value::String("x".into())),
+                    (
+                        "note".to_string(),
+                        Value::Array(vec![Value::Number(1.into()), Value::String("b".into())])
+                    ),
+                ]
+                .into_iter()
+                .collect()
+            )
+        );
+        assert_eq!(
+            fixes.iter().filter(|f| **f == Fixes::TupleToArray).count(),
+            1
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn triple_quoted_strings() -> Result<()> {
+        let (value, fixes) = parse_one("{\"a\": \"\"\"say \"hi\"\nthere\"\"\", 'b': '''x'''}")?;
+        assert_eq!(
+            value,
+            Value::Object(
+                [
+                    ("a".to_string(), Value::String("say \"hi\"\nthere".into())),
+                    ("b".to_string(), Value::String("x".into())),
+                ]
+                .into_iter()
+                .collect()
+            )
+        );
+        assert!(fixes.contains(&Fixes::TripleQuotedString));
+        Ok(())
+    }
+
+    #[test]
+    fn json5_numbers() -> Result<()> {
+        let (value, fixes) = parse_one(r#"[0x1F, -0x10, +5, 10+, NaN, -Infinity, inf, .5, 1]"#)?;
+        assert_eq!(
+            value,
+            Value::Array(vec![
+                Value::Number(31.into()),
+                Value::Number((-16).into()),
+                Value::Number(5.into()),
+                Value::String("10+".into()),
+                Value::String("NaN".into()),
+                Value::String("-Infinity".into()),
+                Value::String("inf".into()),
+                Value::Number(serde_json::Number::from_f64(0.5).unwrap()),
+                Value::Number(1.into()),
+            ])
+        );
+        assert!(fixes.contains(&Fixes::HexNumber("0x1F".into())));
+        assert!(fixes.contains(&Fixes::SignedNumber("+5".into())));
+        assert!(!fixes.contains(&Fixes::SignedNumber("10+".into())));
+        assert!(fixes.contains(&Fixes::NonFiniteNumber("NaN".into())));
+        Ok(())
+    }
+
+    #[test]
+    fn incremental_matches_parse() {
+        let raw = "text {a: b c, 'd': (1, [2, \"x, y\"]), /* c */ \"e\": \"\"\"f\"\"\", g: null // h\n} [1, {i: j}";
+        let mut incremental = IncrementalParser::default();
+        for (idx, c) in raw.char_indices() {
+            let prefix = &raw[..idx + c.len_utf8()];
+            incremental.advance(prefix, &ParseOptions::default());
+            let expected = parse(prefix, &ParseOptions::default()).ok();
+            assert_eq!(
+                incremental.parse(prefix, &ParseOptions::default()).ok(),
+                expected,
+                "after {:?}",
+                prefix
+            );
+        }
+    }
+
+    #[test]
+    fn top_level_parenthesis_is_not_a_tuple() -> Result<()> {
+        // Text around the JSON, only the tuple inside it is converted.
+        let (value, fixes) = parse_one(r#"(1, 2) {"a": (3, 4)}"#)?;
+        assert_eq!(
+            value,
+            Value::Object(vec![(
+                "a".to_string(),
+                Value::Array(vec![Value::Number(3.into()), Value::Number(4.into())])
+            )])
+        );
+        assert_eq!(fixes, vec![Fixes::TupleToArray]);
+
+        assert!(parse("(1, 2)", &ParseOptions::default()).is_err());
+        Ok(())
+    }
+}
@@ -1,14 +1,20 @@
-use baml_types::BamlMap;
+use crate::jsonish::{
+    value::{Fixes, OpenCollection, Span},
+    Value,
+};
 
-use crate::jsonish::Value;
-
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum JsonCollection {
-    // Key, Value
-    Object(Vec<String>, Vec<Value>),
+    // Key and its span, Value
+    Object(Vec<(String, Span)>, Vec<Value>),
     Array(Vec<Value>),
+    // Python style tuple: (1, 2)
+    Tuple(Vec<Value>),
     QuotedString(String),
     SingleQuotedString(String),
+    // Python style """...""" and '''...'''
+    TripleQuotedString(String),
+    TripleSingleQuotedString(String),
     // Handles numbers, booleans, null, and unquoted strings
     UnquotedString(String),
     // Starting with // or #
@@ -22,48 +28,149 @@ impl JsonCollection {
         match self {
             JsonCollection::Object(_, _) => "Object",
             JsonCollection::Array(_) => "Array",
+            JsonCollection::Tuple(_) => "Array",
             JsonCollection::QuotedString(_) => "String",
             JsonCollection::SingleQuotedString(_) => "String",
+            JsonCollection::TripleQuotedString(_) => "String",
+            JsonCollection::TripleSingleQuotedString(_) => "String",
             JsonCollection::UnquotedString(_) => "UnquotedString",
             JsonCollection::TrailingComment(_) => "Comment",
             JsonCollection::BlockComment(_) => "Comment",
         }
     }
-}
 
-impl From<JsonCollection> for Option<Value> {
-    fn from(collection: JsonCollection) -> Option<Value> {
-        Some(match collection {
+    /// What a value is cut off inside of if the input ends while this is open.
+    /// Unquoted strings end with the input anyway, and comments are no value.
+    pub fn open_collection(&self) -> Option<OpenCollection> {
+        match self {
+            JsonCollection::Object(_, _) => Some(OpenCollection::Object),
+            JsonCollection::Array(_) | JsonCollection::Tuple(_) => Some(OpenCollection::Array),
+            JsonCollection::QuotedString(_)
+            | JsonCollection::SingleQuotedString(_)
+            | JsonCollection::TripleQuotedString(_)
+            | JsonCollection::TripleSingleQuotedString(_) => Some(OpenCollection::String),
+            JsonCollection::UnquotedString(_)
+            | JsonCollection::TrailingComment(_)
+            | JsonCollection::BlockComment(_) => None,
+        }
+    }
+
+    /// The number of keys and values held by an object, array or tuple.
+    pub fn item_counts(&self) -> (usize, usize) {
+        match self {
+            JsonCollection::Object(keys, values) => (keys.len(), values.len()),
+            JsonCollection::Array(values) | JsonCollection::Tuple(values) => (0, values.len()),
+            _ => (0, 0),
+        }
+    }
+
+    /// Drops the items added after `item_counts` returned `counts`.
+    pub fn truncate_items(&mut self, counts: (usize, usize)) {
+        match self {
+            JsonCollection::Object(keys, values) => {
+                keys.truncate(counts.0);
+                values.truncate(counts.1);
+            }
+            JsonCollection::Array(values) | JsonCollection::Tuple(values) => {
+                values.truncate(counts.1)
+            }
+            _ => {}
+        }
+    }
+
+    /// Converts the collection into a value, along with any fixes that were
+    /// needed to interpret it. Comments produce no value.
+    pub fn into_value(self) -> Option<(Value, Vec<Fixes>)> {
+        Some(match self {
             JsonCollection::TrailingComment(_) | JsonCollection::BlockComment(_) => return None,
             JsonCollection::Object(keys, values) => {
-                // log::info!("keys: {:?}", keys);
-                let mut object = BamlMap::new();
-                for (key, value) in keys.into_iter().zip(values.into_iter()) {
-                    object.insert(key, value);
-                }
-                Value::Object(object)
+                // Repeated keys are kept, and resolved when coercing.
+                (
+                    Value::Object(
+                        keys.into_iter()
+                            .zip(values)
+                            .map(|((k, span), v)| (k, v.with_key_span(span)))
+                            .collect(),
+                    ),
+                    vec![],
+                )
             }
-            JsonCollection::Array(values) => Value::Array(values),
-            JsonCollection::QuotedString(s) => Value::String(s),
-            JsonCollection::SingleQuotedString(s) => Value::String(s),
-            JsonCollection::UnquotedString(s) => {
-                let s = s.trim();
-                if s == "true" {
-                    Value::Boolean(true)
-                } else if s == "false" {
-                    Value::Boolean(false)
-                } else if s == "null" {
-                    Value::Null
-                } else if let Ok(n) = s.parse::<i64>() {
-                    Value::Number(n.into())
-                } else if let Ok(n) = s.parse::<u64>() {
-                    Value::Number(n.into())
-                } else if let Ok(n) = s.parse::<f64>() {
-                    Value::Number(serde_json::Number::from_f64(n).unwrap())
-                } else {
-                    Value::String(s.into())
-                }
+            JsonCollection::Array(values) => (Value::Array(values), vec![]),
+            JsonCollection::Tuple(values) => (Value::Array(values), vec![Fixes::TupleToArray]),
+            JsonCollection::QuotedString(s) => (Value::String(s), vec![]),
+            JsonCollection::SingleQuotedString(s) => (Value::String(s), vec![]),
+            JsonCollection::TripleQuotedString(s) | JsonCollection::TripleSingleQuotedString(s) => {
+                (Value::String(s), vec![Fixes::TripleQuotedString])
             }
+            JsonCollection::UnquotedString(s) => unquoted_value(s.trim()),
         })
     }
 }
+
+fn unquoted_value(s: &str) -> (Value, Vec<Fixes>) {
+    match s {
+        "true" => return (Value::Boolean(true), vec![]),
+        "false" => return (Value::Boolean(false), vec![]),
+        "null" => return (Value::Null, vec![]),
+        "True" => return (Value::Boolean(true), vec![Fixes::PythonConstant(s.into())]),
+        "False" => return (Value::Boolean(false), vec![Fixes::PythonConstant(s.into())]),
+        "None" => return (Value::Null, vec![Fixes::PythonConstant(s.into())]),
+        // serde_json numbers can't hold these, so we keep them as strings and let
+        // the float coercer parse them.
+        "NaN" | "Infinity" | "-Infinity" | "+Infinity" | "nan" | "inf" | "-inf" => {
+            return (
+                Value::String(s.into()),
+                vec![Fixes::NonFiniteNumber(s.into())],
+            )
+        }
+        _ => {}
+    }
+
+    if let Some(v) = parse_number(s) {
+        return (v, vec![]);
+    }
+
+    // 0x1F, -0x1F
+    let (negative, unsigned) = match s.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, s.strip_prefix('+').unwrap_or(s)),
+    };
+    if let Some(hex) = unsigned
+        .strip_prefix("0x")
+        .or_else(|| unsigned.strip_prefix("0X"))
+    {
+        if let Ok(n) = i64::from_str_radix(hex, 16) {
+            let n = if negative { -n } else { n };
+            return (Value::Number(n.into()), vec![Fixes::HexNumber(s.into())]);
+        }
+    }
+
+    // +1 (JSON5). A trailing '+' ("10+", as in "10 or more") is left as text.
+    if let Some(unsigned) = s.strip_prefix('+').map(str::trim) {
+        if !unsigned.starts_with(['+', '-']) {
+            if let Some(v) = parse_number(unsigned) {
+                return (v, vec![Fixes::SignedNumber(s.into())]);
+            }
+        }
+    }
+
+    (Value::String(s.into()), vec![])
+}
+
+fn parse_number(s: &str) -> Option<Value> {
+    // Rust accepts a leading '+' which JSON does not, so we handle it separately.
+    if s.starts_with('+') {
+        return None;
+    }
+
+    if let Ok(n) = s.parse::<i64>() {
+        Some(Value::Number(n.into()))
+    } else if let Ok(n) = s.parse::<u64>() {
+        Some(Value::Number(n.into()))
+    } else if let Ok(n) = s.parse::<f64>() {
+        // Non-finite values ("inf", "NaN", ...) can't be represented as JSON numbers.
+        serde_json::Number::from_f64(n).map(Value::Number)
+    } else {
+        None
+    }
+}
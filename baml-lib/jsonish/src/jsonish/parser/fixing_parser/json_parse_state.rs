@@ -1,15 +1,33 @@
-use std::iter::Peekable;
+use std::{cell::Cell, iter::Peekable};
 
-use crate::jsonish::{value::Fixes, Value};
+use crate::jsonish::{parser::ParseOptions, value::Fixes, Value};
 use anyhow::Result;
 
 use super::json_collection::JsonCollection;
 
+#[derive(Clone, Default)]
 pub struct JsonParseState {
-    pub collection_stack: Vec<(JsonCollection, Vec<Fixes>)>,
+    // Each collection with its fixes and where it starts.
+    pub collection_stack: Vec<(JsonCollection, Vec<Fixes>, usize)>,
 
     // Technically we may find multiple values in a single string
     pub completed_values: Vec<(&'static str, Value, Vec<Fixes>)>,
+
+    // Where the token being processed is. Once all the input is processed,
+    // where its trailing whitespace starts, which is where anything left open
+    // ends.
+    pub pos: usize,
+}
+
+/// A point in the input where nothing parsed so far depends on the text after
+/// it, so parsing can resume there once more text arrives.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Checkpoint {
+    pub pos: usize,
+    stack_len: usize,
+    top_items: (usize, usize),
+    top_fixes: usize,
+    completed: usize,
 }
 
 impl JsonParseState {
@@ -17,64 +35,200 @@ impl JsonParseState {
         JsonParseState {
             collection_stack: vec![],
             completed_values: vec![],
+            pos: 0,
+        }
+    }
+
+    /// Processes `str[from..]`, looking ahead into the rest of `str`, and
+    /// returns the last checkpoint passed. Once a token has looked ahead up to
+    /// the end of `str`, what follows may change with more input, so there are
+    /// no checkpoints after it.
+    pub fn process(
+        &mut self,
+        str: &str,
+        from: usize,
+        options: &ParseOptions,
+    ) -> Result<Option<Checkpoint>> {
+        let mut checkpoint = None;
+        let reached_end = Cell::new(false);
+        let mut chars = str[from..].char_indices().peekable();
+        let mut tokens = 0usize;
+        while let Some((count, c)) = chars.next() {
+            // Looking at the clock for every token would slow parsing down.
+            tokens += 1;
+            if tokens.is_multiple_of(1024) {
+                options.deadline.check()?;
+            }
+            let count = from + count;
+            self.pos = count;
+            let resumable = self.is_resumable_token(c);
+            let peekable = str[count + c.len_utf8()..]
+                .char_indices()
+                .chain(std::iter::from_fn(|| {
+                    reached_end.set(true);
+                    None
+                }))
+                .peekable();
+            let increments = self.process_token(c, peekable)?;
+            self.check_limits(options)?;
+            for _ in 0..increments {
+                chars.next();
+            }
+            if resumable && !reached_end.get() {
+                checkpoint = Some(self.checkpoint(count + c.len_utf8()));
+            }
+        }
+        self.pos = str.trim_end().len();
+        Ok(checkpoint)
+    }
+
+    fn check_limits(&self, options: &ParseOptions) -> Result<()> {
+        let limits = &options.limits;
+        limits.check_depth(self.collection_stack.len())?;
+        if let Some((top, _, _)) = self.collection_stack.last() {
+            let (keys, values) = top.item_counts();
+            limits.check_items(keys.max(values))?;
+        }
+        limits.check_candidate_count(self.completed_values.len())?;
+        Ok(())
+    }
+
+    /// Tokens that open or close a collection, or separate its items. They are
+    /// handled without looking ahead, so only what came before them can
+    /// depend on the rest of the input.
+    fn is_resumable_token(&self, token: char) -> bool {
+        match self.collection_stack.last() {
+            None => matches!(token, '{' | '['),
+            Some((JsonCollection::Object(_, _), _, _)) => {
+                matches!(token, '{' | '[' | '(' | '}' | ',' | ':')
+            }
+            Some((JsonCollection::Array(_), _, _)) => matches!(token, '{' | '[' | '(' | ']' | ','),
+            Some((JsonCollection::Tuple(_), _, _)) => matches!(token, '{' | '[' | '(' | ')' | ','),
+            Some(_) => false,
+        }
+    }
+
+    fn checkpoint(&self, pos: usize) -> Checkpoint {
+        let (top_items, top_fixes) = self
+            .collection_stack
+            .last()
+            .map_or(((0, 0), 0), |(c, fixes, _)| (c.item_counts(), fixes.len()));
+        Checkpoint {
+            pos,
+            stack_len: self.collection_stack.len(),
+            top_items,
+            top_fixes,
+            completed: self.completed_values.len(),
+        }
+    }
+
+    /// Undoes everything processed after `checkpoint`. Until the next
+    /// checkpoint, tokens only push strings or comments, which complete into
+    /// the collection on top of the stack, so truncating is enough.
+    pub fn restore(&mut self, checkpoint: &Checkpoint) {
+        self.collection_stack.truncate(checkpoint.stack_len);
+        if let Some((collection, fixes, _)) = self.collection_stack.last_mut() {
+            collection.truncate_items(checkpoint.top_items);
+            fixes.truncate(checkpoint.top_fixes);
         }
+        self.completed_values.truncate(checkpoint.completed);
+    }
+
+    /// Pops the collection on top of the stack, which ends at `end`, into its
+    /// parent or the completed values.
+    pub fn complete_collection(&mut self, end: usize) -> Result<()> {
+        self.close_collection(end, false)
     }
 
-    pub fn complete_collection(&mut self) {
-        let (collection, fixes) = match self.collection_stack.pop() {
+    /// Closes everything left open once the input ran out, marking the values
+    /// that were cut off.
+    pub fn complete_truncated(&mut self) -> Result<()> {
+        while !self.collection_stack.is_empty() {
+            self.close_collection(self.pos, true)?;
+        }
+        Ok(())
+    }
+
+    fn close_collection(&mut self, end: usize, truncated: bool) -> Result<()> {
+        let (collection, fixes, start) = match self.collection_stack.pop() {
             Some(collection) => collection,
-            None => return,
+            None => return Ok(()),
         };
 
         let name = collection.name();
+        let open = collection.open_collection().filter(|_| truncated);
+        let span = match &collection {
+            // Closed by looking ahead, so only its content tells where it ends.
+            JsonCollection::UnquotedString(s) => start..start + s.trim_end().len(),
+            _ => start..end,
+        };
 
-        let value: Value = match collection.into() {
-            Some(value) => value,
-            None => return,
+        let (value, mut fixes) = match collection.into_value() {
+            Some((value, value_fixes)) => {
+                let mut all_fixes = value_fixes;
+                all_fixes.extend(fixes);
+                (value, all_fixes)
+            }
+            None => return Ok(()),
+        };
+        // Marked on the value itself, unlike fixes which the outermost value
+        // collects.
+        let value = match open {
+            Some(open) => Value::FixedJson(value.into(), vec![Fixes::Truncated(open)]),
+            None => value,
         };
 
-        if let Some((last, _fixes)) = self.collection_stack.last_mut() {
+        if let Some((last, parent_fixes, _)) = self.collection_stack.last_mut() {
+            // Nested fixes are reported on the outermost value.
+            parent_fixes.append(&mut fixes);
             match last {
                 JsonCollection::Object(keys, values) => {
                     if keys.len() == values.len() {
                         match value {
-                            Value::String(s) => keys.push(s),
-                            Value::AnyOf(_, s) => keys.push(s),
-                            _ => keys.push(value.to_string()),
+                            Value::String(s) => keys.push((s, span)),
+                            Value::AnyOf(_, s) => keys.push((s, span)),
+                            _ => keys.push((value.to_string(), span)),
                         }
                     } else {
-                        values.push(value);
+                        values.push(value.spanned(span));
                     }
                 }
-                JsonCollection::Array(values) => {
-                    values.push(value);
+                JsonCollection::Array(values) | JsonCollection::Tuple(values) => {
+                    values.push(value.spanned(span));
                 }
                 _ => {
                     // TODO: this should never happen as we should only be pushing objects and arrays
-                    panic!(
+                    anyhow::bail!(
                         "Unexpected value: {:?} in collection stack: {:?}",
-                        value, last
+                        value,
+                        last
                     );
                 }
             }
         } else {
-            self.completed_values.push((name, value, fixes));
+            self.completed_values
+                .push((name, value.spanned(span), fixes));
         }
+        Ok(())
     }
 
     fn consume(&mut self, token: char) -> Result<usize> {
-        let (last, _) = self.collection_stack.last_mut().unwrap();
+        let Some((last, _, _)) = self.collection_stack.last_mut() else {
+            anyhow::bail!("Unexpected token: {:?} outside of any value", token);
+        };
         match last {
             JsonCollection::QuotedString(s)
             | JsonCollection::BlockComment(s)
             | JsonCollection::SingleQuotedString(s)
+            | JsonCollection::TripleQuotedString(s)
+            | JsonCollection::TripleSingleQuotedString(s)
             | JsonCollection::UnquotedString(s)
             | JsonCollection::TrailingComment(s) => {
                 // println!("Consuming: {s} + {:?}", token);
                 s.push(token);
             }
             _ => {
-                panic!("Unexpected token: {:?} in: {:?}", token, last);
+                anyhow::bail!("Unexpected token: {:?} in: {:?}", token, last);
             }
         }
         Ok(0)
@@ -112,7 +266,7 @@ impl JsonParseState {
         let pos = if self.collection_stack.len() >= 2 {
             self.collection_stack
                 .get(self.collection_stack.len() - 2)
-                .map(|(c, _)| match c {
+                .map(|(c, _, _)| match c {
                     JsonCollection::Object(keys, values) => {
                         if keys.len() == values.len() {
                             2
@@ -121,6 +275,7 @@ impl JsonParseState {
                         }
                     }
                     JsonCollection::Array(_) => 4,
+                    JsonCollection::Tuple(_) => 5,
                     _ => 1,
                 })
                 .unwrap()
@@ -192,7 +347,7 @@ impl JsonParseState {
                                                         // let _ = self.consume(c);
                                                     }
                                                 },
-                                                '"' => {
+                                                '"' | '\'' => {
                                                     // This is likely a new key
                                                     log::debug!("Closing due to: new key after space + comma");
                                                     return Some(idx);
@@ -238,6 +393,20 @@ impl JsonParseState {
                 }
                 Some(counter)
             }
+            5 => {
+                // in tuple
+                let mut counter = 0;
+                for (idx, c) in next {
+                    counter = idx;
+                    match c {
+                        ',' | ')' => return Some(idx),
+                        x => {
+                            let _ = self.consume(x);
+                        }
+                    }
+                }
+                Some(counter)
+            }
             _ => unreachable!("Invalid position"),
         }
     }
@@ -251,7 +420,7 @@ impl JsonParseState {
             if self.collection_stack.len() >= 2 {
                 self.collection_stack
                     .get(self.collection_stack.len() - 2)
-                    .map(|(c, _)| match c {
+                    .map(|(c, _, _)| match c {
                         JsonCollection::Object(keys, values) => {
                             if keys.len() == values.len() {
                                 (true, false, false)
@@ -259,7 +428,7 @@ impl JsonParseState {
                                 (false, true, true)
                             }
                         }
-                        JsonCollection::Array(_) => (false, false, true),
+                        JsonCollection::Array(_) | JsonCollection::Tuple(_) => (false, false, true),
                         _ => (false, false, false),
                     })
                     .map(|(a, b, c)| (true, a, b, c))
@@ -267,6 +436,10 @@ impl JsonParseState {
             } else {
                 (false, false, false, false)
             };
+        let array_end = match self.collection_stack.iter().rev().nth(1) {
+            Some((JsonCollection::Tuple(_), _, _)) => ')',
+            _ => ']',
+        };
 
         if let Some((idx, next_char)) = next.peek() {
             let _idx = *idx;
@@ -281,7 +454,7 @@ impl JsonParseState {
                     log::debug!("Closing due to: value",);
                     true
                 }
-                ',' | ']' if in_array => {
+                x if in_array && (*x == ',' || *x == array_end) => {
                     // We're ready to close the value
                     log::debug!("Closing due to: array");
                     true
@@ -294,7 +467,7 @@ impl JsonParseState {
                             '}' if in_object_key || in_object_value => return true,
                             ':' if in_object_key => return true,
                             ',' if in_object_value => return true,
-                            ',' | ']' if in_array => return true,
+                            x if in_array && (x == ',' || x == array_end) => return true,
                             '/' => {
                                 // Could be a comment
                                 match next.peek() {
@@ -340,16 +513,16 @@ impl JsonParseState {
     pub fn process_token(
         &mut self,
         token: char,
-        mut next: Peekable<impl Iterator<Item = (usize, char)>>,
+        mut next: Peekable<impl Iterator<Item = (usize, char)> + Clone>,
     ) -> Result<usize> {
         // println!("Processing: {:?}..{:?}", token, next.peek());
-        if let Some((last, _)) = self.collection_stack.last() {
+        if let Some((last, _, _)) = self.collection_stack.last() {
             match last {
                 JsonCollection::Object(_, _) => {
                     match token {
                         '}' => {
                             // We're ready to close the object
-                            self.complete_collection();
+                            self.complete_collection(self.pos + 1)?;
                             Ok(0)
                         }
                         // We can safely ignore these tokens
@@ -366,7 +539,7 @@ impl JsonParseState {
                     match token {
                         ']' => {
                             // We're ready to close the array
-                            self.complete_collection();
+                            self.complete_collection(self.pos + 1)?;
                             Ok(0)
                         }
                         // Skip these tokens
@@ -374,6 +547,17 @@ impl JsonParseState {
                         _ => self.find_any_starting_value(token, next),
                     }
                 }
+                JsonCollection::Tuple(_) => {
+                    // Same as an array, but closed by a parenthesis
+                    match token {
+                        ')' => {
+                            self.complete_collection(self.pos + 1)?;
+                            Ok(0)
+                        }
+                        ',' => Ok(0),
+                        _ => self.find_any_starting_value(token, next),
+                    }
+                }
                 JsonCollection::QuotedString(_) => {
                     // We could be expecting:
                     // - A closing quote
@@ -383,7 +567,7 @@ impl JsonParseState {
                             // It's possible that the LLM messed up the escaping
                             // We'll try to fix it.
                             if self.should_close_string(next, '"') {
-                                self.complete_collection();
+                                self.complete_collection(self.pos + 1)?;
                                 Ok(0)
                             } else {
                                 self.consume(token)
@@ -402,7 +586,7 @@ impl JsonParseState {
                             // It's possible that the LLM messed up the escaping
                             // We'll try to fix it.
                             if self.should_close_string(next, '\'') {
-                                self.complete_collection();
+                                self.complete_collection(self.pos + 1)?;
                                 Ok(0)
                             } else {
                                 self.consume(token)
@@ -411,13 +595,30 @@ impl JsonParseState {
                         _ => self.consume(token),
                     }
                 }
+                JsonCollection::TripleQuotedString(_)
+                | JsonCollection::TripleSingleQuotedString(_) => {
+                    // Only the same three quotes in a row close the string
+                    let quote = match last {
+                        JsonCollection::TripleQuotedString(_) => '"',
+                        _ => '\'',
+                    };
+                    if token == quote
+                        && matches!(next.next(), Some((_, c)) if c == quote)
+                        && matches!(next.next(), Some((_, c)) if c == quote)
+                    {
+                        self.complete_collection(self.pos + 3)?;
+                        Ok(2)
+                    } else {
+                        self.consume(token)
+                    }
+                }
                 JsonCollection::UnquotedString(_) => {
                     // We could be expecting:
                     // - A terminating json character (comma, colon, bracket, space, newline)
                     // - A character
                     let res = self.consume(token);
                     if let Some(count) = self.should_close_unescaped_string(next) {
-                        self.complete_collection();
+                        self.complete_collection(self.pos)?;
                         Ok(count)
                     } else {
                         res
@@ -430,7 +631,7 @@ impl JsonParseState {
                     match token {
                         '\n' => {
                             // We're ready to close the comment
-                            self.complete_collection();
+                            self.complete_collection(self.pos)?;
                             Ok(0)
                         }
                         _ => self.consume(token),
@@ -446,7 +647,7 @@ impl JsonParseState {
                             match next.peek() {
                                 Some((_, '/')) => {
                                     // We're ready to close the comment
-                                    self.complete_collection();
+                                    self.complete_collection(self.pos + 2)?;
                                     Ok(1)
                                 }
                                 _ => Ok(0),
@@ -469,27 +670,57 @@ impl JsonParseState {
     fn find_any_starting_value(
         &mut self,
         token: char,
-        mut next: Peekable<impl Iterator<Item = (usize, char)>>,
+        mut next: Peekable<impl Iterator<Item = (usize, char)> + Clone>,
     ) -> Result<usize> {
         match token {
             '{' => {
-                self.collection_stack
-                    .push((JsonCollection::Object(vec![], vec![]), Default::default()));
+                self.collection_stack.push((
+                    JsonCollection::Object(vec![], vec![]),
+                    Default::default(),
+                    self.pos,
+                ));
             }
             '[' => {
-                self.collection_stack
-                    .push((JsonCollection::Array(vec![]), Default::default()));
+                self.collection_stack.push((
+                    JsonCollection::Array(vec![]),
+                    Default::default(),
+                    self.pos,
+                ));
+            }
+            // Tuples are only considered inside of another collection, otherwise
+            // any text starting with a parenthesis would become an array. Text
+            // like "(unknown)" stays a string.
+            '(' if !self.collection_stack.is_empty() && opens_tuple(next.clone()) => {
+                self.collection_stack.push((
+                    JsonCollection::Tuple(vec![]),
+                    Default::default(),
+                    self.pos,
+                ));
+            }
+            '"' | '\'' if starts_triple_quote(token, &mut next) => {
+                self.collection_stack.push((
+                    if token == '"' {
+                        JsonCollection::TripleQuotedString(String::new())
+                    } else {
+                        JsonCollection::TripleSingleQuotedString(String::new())
+                    },
+                    Default::default(),
+                    self.pos,
+                ));
+                return Ok(2);
             }
             '"' => {
                 self.collection_stack.push((
                     JsonCollection::QuotedString(String::new()),
                     Default::default(),
+                    self.pos,
                 ));
             }
             '\'' => {
                 self.collection_stack.push((
                     JsonCollection::SingleQuotedString(String::new()),
                     Default::default(),
+                    self.pos,
                 ));
             }
             '/' => {
@@ -499,6 +730,7 @@ impl JsonParseState {
                         self.collection_stack.push((
                             JsonCollection::TrailingComment(String::new()),
                             Default::default(),
+                            self.pos,
                         ));
                         return Ok(1);
                     }
@@ -506,6 +738,7 @@ impl JsonParseState {
                         self.collection_stack.push((
                             JsonCollection::BlockComment(String::new()),
                             Default::default(),
+                            self.pos,
                         ));
                         return Ok(1);
                     }
@@ -514,10 +747,13 @@ impl JsonParseState {
             }
             x if x.is_whitespace() => {}
             x => {
-                self.collection_stack
-                    .push((JsonCollection::UnquotedString(x.into()), Default::default()));
+                self.collection_stack.push((
+                    JsonCollection::UnquotedString(x.into()),
+                    Default::default(),
+                    self.pos,
+                ));
                 if let Some(count) = self.should_close_unescaped_string(next) {
-                    self.complete_collection();
+                    self.complete_collection(self.pos)?;
                     return Ok(count);
                 }
             }
@@ -526,3 +762,39 @@ impl JsonParseState {
         return Ok(0);
     }
 }
+
+/// Whether a `,` comes before the `)` closing the parenthesis just read,
+/// outside of any nested collection or string.
+fn opens_tuple(next: impl Iterator<Item = (usize, char)>) -> bool {
+    let mut depth = 0usize;
+    let mut quote = None;
+    let mut escaped = false;
+    for (_, c) in next {
+        if let Some(q) = quote {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                c if c == q => quote = None,
+                _ => {}
+            }
+            continue;
+        }
+        match c {
+            '"' | '\'' => quote = Some(c),
+            '(' | '[' | '{' => depth += 1,
+            ')' | ']' | '}' if depth == 0 => return false,
+            ')' | ']' | '}' => depth -= 1,
+            ',' if depth == 0 => return true,
+            _ => {}
+        }
+    }
+    false
+}
+
+fn starts_triple_quote(
+    quote: char,
+    next: &mut Peekable<impl Iterator<Item = (usize, char)>>,
+) -> bool {
+    matches!(next.next(), Some((_, c)) if c == quote)
+        && matches!(next.next(), Some((_, c)) if c == quote)
+}
@@ -0,0 +1,220 @@
+use std::time::{Duration, Instant};
+
+use crate::jsonish::Value;
+
+/// Bounds on the work done for one response, so that untrusted model output
+/// can't make parsing arbitrarily slow, deep or large. Going over any of them
+/// fails the whole parse with a [`LimitExceeded`] error.
+#[derive(Debug, Clone)]
+pub struct ParseLimits {
+    /// Longest response, in bytes.
+    pub max_input_bytes: usize,
+    /// Deepest nesting of arrays and objects. Also bounds how many times text
+    /// found inside the response, like a fenced block, is parsed again.
+    pub max_depth: usize,
+    /// Most ways a response may be read, e.g. one per JSON object in prose.
+    pub max_candidates: usize,
+    /// Most items in one array or object.
+    pub max_collection_items: usize,
+    /// Wall-clock time to parse and coerce one response.
+    pub time_budget: Option<Duration>,
+}
+
+impl Default for ParseLimits {
+    fn default() -> Self {
+        Self {
+            max_input_bytes: 16 * 1024 * 1024,
+            max_depth: 100,
+            max_candidates: 1000,
+            max_collection_items: 100_000,
+            time_budget: None,
+        }
+    }
+}
+
+/// The limit a response went over.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LimitExceeded {
+    InputBytes(usize),
+    Depth(usize),
+    Candidates(usize),
+    CollectionItems(usize),
+    TimeBudget(Duration),
+}
+
+impl std::fmt::Display for LimitExceeded {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LimitExceeded::InputBytes(max) => write!(f, "Response is longer than {} bytes", max),
+            LimitExceeded::Depth(max) => write!(f, "Response nests deeper than {} levels", max),
+            LimitExceeded::Candidates(max) => {
+                write!(f, "Response can be read more than {} ways", max)
+            }
+            LimitExceeded::CollectionItems(max) => {
+                write!(f, "Response has a collection of more than {} items", max)
+            }
+            LimitExceeded::TimeBudget(budget) => {
+                write!(f, "Response took longer than {:?} to parse", budget)
+            }
+        }
+    }
+}
+
+impl std::error::Error for LimitExceeded {}
+
+impl ParseLimits {
+    /// Starts the clock on `time_budget`.
+    pub(crate) fn deadline(&self) -> Deadline {
+        Deadline(
+            self.time_budget
+                .map(|budget| (Instant::now() + budget, budget)),
+        )
+    }
+
+    pub(super) fn check_input(&self, str: &str) -> Result<(), LimitExceeded> {
+        match str.len() > self.max_input_bytes {
+            true => Err(LimitExceeded::InputBytes(self.max_input_bytes)),
+            false => Ok(()),
+        }
+    }
+
+    pub(super) fn check_depth(&self, depth: usize) -> Result<(), LimitExceeded> {
+        match depth > self.max_depth {
+            true => Err(LimitExceeded::Depth(self.max_depth)),
+            false => Ok(()),
+        }
+    }
+
+    pub(super) fn check_items(&self, items: usize) -> Result<(), LimitExceeded> {
+        match items > self.max_collection_items {
+            true => Err(LimitExceeded::CollectionItems(self.max_collection_items)),
+            false => Ok(()),
+        }
+    }
+
+    /// Counts the candidates of every `AnyOf` in `value`.
+    pub(super) fn check_candidates(&self, value: &Value) -> Result<(), LimitExceeded> {
+        self.check_candidate_count(count_candidates(value))
+    }
+
+    pub(super) fn check_candidate_count(&self, candidates: usize) -> Result<(), LimitExceeded> {
+        match candidates > self.max_candidates {
+            true => Err(LimitExceeded::Candidates(self.max_candidates)),
+            false => Ok(()),
+        }
+    }
+
+    /// Checks a value serde_json parsed, which has limits of its own that may
+    /// be looser.
+    pub(super) fn check_value(&self, value: &Value, depth: usize) -> Result<(), LimitExceeded> {
+        let items = match value.unspanned() {
+            Value::Object(entries) => entries.iter().map(|(_, v)| v).collect(),
+            Value::Array(items) => items.iter().collect(),
+            _ => vec![],
+        };
+        if items.is_empty() {
+            return Ok(());
+        }
+        self.check_depth(depth + 1)?;
+        self.check_items(items.len())?;
+        items
+            .into_iter()
+            .try_for_each(|v| self.check_value(v, depth + 1))
+    }
+}
+
+fn count_candidates(value: &Value) -> usize {
+    match value {
+        Value::AnyOf(items, _) => items.len() + items.iter().map(count_candidates).sum::<usize>(),
+        Value::Object(entries) => entries.iter().map(|(_, v)| count_candidates(v)).sum(),
+        Value::Array(items) => items.iter().map(count_candidates).sum(),
+        Value::Markdown(_, v) | Value::FixedJson(v, _) | Value::Spanned(v, _, _) => {
+            count_candidates(v)
+        }
+        Value::String(_) | Value::Number(_) | Value::Boolean(_) | Value::Null => 0,
+    }
+}
+
+/// When the time budget of [`ParseLimits`] runs out, if there is one.
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct Deadline(Option<(Instant, Duration)>);
+
+impl Deadline {
+    pub fn check(&self) -> Result<(), LimitExceeded> {
+        match self.0 {
+            Some((deadline, budget)) if Instant::now() >= deadline => {
+                Err(LimitExceeded::TimeBudget(budget))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::jsonish::parser::{entry, ParseOptions};
+    use test_log::test;
+
+    fn parse(str: &str, limits: ParseLimits) -> Result<Value, LimitExceeded> {
+        let deadline = limits.deadline();
+        entry::parse(str, ParseOptions::default().with_limits(limits, deadline))
+            .map_err(|e| e.downcast().expect("a limit"))
+    }
+
+    #[test]
+    fn limits_fail_the_parse() {
+        let limits = || ParseLimits {
+            max_input_bytes: 64,
+            max_depth: 3,
+            max_candidates: 8,
+            max_collection_items: 3,
+            time_budget: None,
+        };
+
+        assert_eq!(
+            parse(&"a".repeat(65), limits()),
+            Err(LimitExceeded::InputBytes(64))
+        );
+        // Valid JSON, fixed JSON and JSON in prose.
+        for raw in ["[[[[1]]]]", "[[[[1", "x [[[[1]]]] y"] {
+            assert_eq!(parse(raw, limits()), Err(LimitExceeded::Depth(3)), "{raw}");
+        }
+        for raw in [
+            "[1, 2, 3, 4]",
+            "[1, 2, 3, 4",
+            r#"{"a": 1, "b": 2, "c": 3, "d": 4"#,
+        ] {
+            assert_eq!(
+                parse(raw, limits()),
+                Err(LimitExceeded::CollectionItems(3)),
+                "{raw}"
+            );
+        }
+        assert_eq!(
+            parse("{} {} {} {}", limits()),
+            Err(LimitExceeded::Candidates(8))
+        );
+        assert_eq!(
+            parse(
+                "[1]",
+                ParseLimits {
+                    time_budget: Some(Duration::ZERO),
+                    ..limits()
+                }
+            ),
+            Err(LimitExceeded::TimeBudget(Duration::ZERO))
+        );
+
+        assert_eq!(parse("x [[[1]]] y {a: [1, 2, 3]}", limits()).err(), None);
+    }
+
+    #[test]
+    fn deep_nesting_is_an_error() {
+        let raw = "[".repeat(100_000);
+        assert_eq!(
+            parse(&raw, ParseLimits::default()),
+            Err(LimitExceeded::Depth(100))
+        );
+    }
+}
@@ -1,9 +1,9 @@
 use crate::jsonish::{
-    parser::{entry, ParsingMode},
+    parser::{entry, spans, ParsingMode},
     Value,
 };
 
-use super::ParseOptions;
+use super::{LimitExceeded, ParseOptions};
 use anyhow::Result;
 
 pub fn parse<'a>(str: &'a str, options: &ParseOptions) -> Result<Vec<(String, Value)>> {
@@ -12,10 +12,8 @@ pub fn parse<'a>(str: &'a str, options: &ParseOptions) -> Result<Vec<(String, Va
     let mut remaining = str;
     // Find regex for markdown blocks (```<tag><EOF|newline>)
 
-    let md_tag_start = regex::Regex::new(r"```([a-zA-Z0-9 ]+)(?:\n|$)")
-        .map_err(|e| anyhow::Error::from(e).context("Failed to build regex for md-tag-start"))?;
-    let md_tag_end = regex::Regex::new(r"```(?:\n|$)")
-        .map_err(|e| anyhow::Error::from(e).context("Failed to build regex for md-tag-end"))?;
+    let md_tag_start = md_tag_start()?;
+    let md_tag_end = md_tag_end()?;
 
     let mut should_loop = true;
 
@@ -23,14 +21,18 @@ pub fn parse<'a>(str: &'a str, options: &ParseOptions) -> Result<Vec<(String, Va
         let tag = cap.as_str();
         // log::info!("Found tag: {:#?}", cap);
 
+        // `remaining` is always the end of `str`.
+        let content_start = str.len() - remaining.len() + cap.end();
         let md_content = if let Some(end) = md_tag_end.find(&remaining[cap.end()..]) {
-            let next = remaining[cap.end()..cap.end() + end.start()].trim();
+            let next = &remaining[cap.end()..cap.end() + end.start()];
             remaining = &remaining[cap.end() + end.end()..];
             next
         } else {
             should_loop = false;
-            remaining[cap.end()..].trim()
+            &remaining[cap.end()..]
         };
+        let content_start = content_start + (md_content.len() - md_content.trim_start().len());
+        let md_content = md_content.trim();
 
         // log::info!("Content:\n-----\n{}\n-----\n", md_content);
 
@@ -41,18 +43,14 @@ pub fn parse<'a>(str: &'a str, options: &ParseOptions) -> Result<Vec<(String, Va
 
         match res {
             Ok(v) => {
-                values.push((
-                    if tag.len() > 3 {
-                        tag[3..].trim()
-                    } else {
-                        "<unspecified>"
-                    }
-                    .to_string(),
-                    v,
-                ));
+                let span = content_start..content_start + md_content.len();
+                values.push((tag_name(tag), spans::nested(v, span)));
             }
             Err(e) => {
                 log::debug!("Error parsing markdown block: Tag: {tag}\n{:?}", e);
+                if e.is::<LimitExceeded>() {
+                    return Err(e);
+                }
             }
         };
 
@@ -68,6 +66,26 @@ pub fn parse<'a>(str: &'a str, options: &ParseOptions) -> Result<Vec<(String, Va
     }
 }
 
+pub(super) fn md_tag_start() -> Result<regex::Regex> {
+    regex::Regex::new(r"```([a-zA-Z0-9 ]+)(?:\n|$)")
+        .map_err(|e| anyhow::Error::from(e).context("Failed to build regex for md-tag-start"))
+}
+
+pub(super) fn md_tag_end() -> Result<regex::Regex> {
+    regex::Regex::new(r"```(?:\n|$)")
+        .map_err(|e| anyhow::Error::from(e).context("Failed to build regex for md-tag-end"))
+}
+
+/// The tag of an opening fence matched by `md_tag_start`.
+pub(super) fn tag_name(fence: &str) -> String {
+    if fence.len() > 3 {
+        fence[3..].trim()
+    } else {
+        "<unspecified>"
+    }
+    .to_string()
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -100,7 +118,7 @@ print("Hello, world!")
             let (tag, value) = &res[0];
             assert_eq!(tag, "json");
 
-            let Value::AnyOf(value, _) = value else {
+            let Value::AnyOf(value, _) = value.unspanned() else {
                 panic!("Expected AnyOf, got {:#?}", value);
             };
             assert!(value.contains(&Value::Object(
@@ -113,7 +131,7 @@ print("Hello, world!")
             let (tag, value) = &res[1];
             assert_eq!(tag, "test json");
 
-            let Value::AnyOf(value, _) = value else {
+            let Value::AnyOf(value, _) = value.unspanned() else {
                 panic!("Expected AnyOf, got {:#?}", value);
             };
             assert!(value.contains(&Value::String("This is a test".to_string())));
@@ -1,17 +1,28 @@
 mod entry;
+mod extraction;
 mod fixing_parser;
+mod limits;
 mod markdown_parser;
 mod multi_json_parser;
+mod spans;
+mod streaming;
 
 pub use entry::parse;
+pub use extraction::ExtractionOptions;
+pub(crate) use limits::Deadline;
+pub use limits::{LimitExceeded, ParseLimits};
+pub use streaming::StreamingParser;
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Debug)]
 pub struct ParseOptions {
     all_finding_all_json_objects: bool,
     allow_markdown_json: bool,
     allow_fixes: bool,
     allow_as_string: bool,
     depth: usize,
+    extraction: ExtractionOptions,
+    limits: ParseLimits,
+    deadline: Deadline,
 }
 
 impl Default for ParseOptions {
@@ -22,6 +33,9 @@ impl Default for ParseOptions {
             allow_fixes: true,
             allow_as_string: true,
             depth: 0,
+            extraction: Default::default(),
+            limits: Default::default(),
+            deadline: Default::default(),
         }
     }
 }
@@ -32,6 +46,18 @@ enum ParsingMode {
 }
 
 impl ParseOptions {
+    pub fn with_extraction(mut self, extraction: ExtractionOptions) -> Self {
+        self.extraction = extraction;
+        self
+    }
+
+    /// `deadline` is when `limits.time_budget` runs out.
+    pub(crate) fn with_limits(mut self, limits: ParseLimits, deadline: Deadline) -> Self {
+        self.limits = limits;
+        self.deadline = deadline;
+        self
+    }
+
     fn next_from_mode(&self, curr_mode: ParsingMode) -> Self {
         let mut new = self.clone();
         match curr_mode {
@@ -1,6 +1,6 @@
 use crate::jsonish::Value;
 
-use super::{entry, ParseOptions};
+use super::{entry, spans, LimitExceeded, ParseOptions};
 use anyhow::Result;
 
 pub fn parse<'a>(str: &'a str, options: &ParseOptions) -> Result<Vec<Value>> {
@@ -18,24 +18,26 @@ pub fn parse<'a>(str: &'a str, options: &ParseOptions) -> Result<Vec<Value>> {
                 stack.push(character);
             }
             '}' | ']' => {
-                if let Some(last) = stack.last() {
-                    let expected_open = if character == '}' { '{' } else { '[' };
-                    if *last == expected_open {
-                        stack.pop();
-                    } else {
-                        return Err(anyhow::anyhow!("Mismatched brackets"));
-                    }
+                let Some(last) = stack.last() else {
+                    // A stray closing bracket in prose.
+                    continue;
+                };
+                let expected_open = if character == '}' { '{' } else { '[' };
+                if *last == expected_open {
+                    stack.pop();
+                } else {
+                    return Err(anyhow::anyhow!("Mismatched brackets"));
                 }
 
-                if stack.is_empty() {
-                    // Assuming json_str_start is never None when stack is empty
+                if let (true, Some(start)) = (stack.is_empty(), json_str_start) {
                     let end_index = index + 1;
-                    let json_str = &str[json_str_start.unwrap()..end_index];
+                    let span = start..end_index;
                     match entry::parse(
-                        json_str,
+                        &str[span.clone()],
                         options.next_from_mode(super::ParsingMode::AllJsonObjects),
                     ) {
-                        Ok(json) => json_objects.push(json),
+                        Ok(json) => json_objects.push(spans::nested(json, span)),
+                        Err(e) if e.is::<LimitExceeded>() => return Err(e),
                         Err(e) => {
                             // Ignore errors
                             log::error!("Failed to parse JSON object: {:?}", e);
@@ -47,14 +49,15 @@ pub fn parse<'a>(str: &'a str, options: &ParseOptions) -> Result<Vec<Value>> {
         }
     }
 
-    if !stack.is_empty() {
+    if let (false, Some(start)) = (stack.is_empty(), json_str_start) {
         // We reached the end but the stack is not empty
-        let json_str = &str[json_str_start.unwrap()..];
+        let span = start..str.len();
         match entry::parse(
-            json_str,
+            &str[span.clone()],
             options.next_from_mode(super::ParsingMode::AllJsonObjects),
         ) {
-            Ok(json) => json_objects.push(json),
+            Ok(json) => json_objects.push(spans::nested(json, span)),
+            Err(e) if e.is::<LimitExceeded>() => return Err(e),
             Err(e) => {
                 // Ignore errors
                 log::error!("Failed to parse JSON object: {:?}", e);
@@ -98,7 +101,7 @@ print("Hello, world!")
         assert_eq!(res.len(), 2);
         {
             let value = &res[0];
-            let Value::AnyOf(value, _) = value else {
+            let Value::AnyOf(value, _) = value.unspanned() else {
                 panic!("Expected AnyOf, got {:#?}", value);
             };
             assert!(value.contains(&Value::Object(
@@ -109,7 +112,7 @@ print("Hello, world!")
         }
         {
             let value = &res[1];
-            let Value::AnyOf(value, _) = value else {
+            let Value::AnyOf(value, _) = value.unspanned() else {
                 panic!("Expected AnyOf, got {:#?}", value);
             };
             assert!(value.contains(&Value::Array(vec![Value::String(
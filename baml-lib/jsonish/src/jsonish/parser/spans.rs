@@ -0,0 +1,225 @@
+use crate::jsonish::{value::Span, Value};
+
+/// Adds spans to a value serde_json parsed out of `str`, which must be the
+/// JSON it was parsed from.
+pub(super) fn locate(value: Value, str: &str) -> Value {
+    let mut scanner = Scanner {
+        bytes: str.as_bytes(),
+        pos: 0,
+    };
+    scanner.value(value)
+}
+
+/// Moves the spans of a value parsed out of `str[range]` to be offsets into
+/// `str`, and spans the value with `range`.
+pub(super) fn nested(mut value: Value, range: Span) -> Value {
+    let offset = range.start;
+    value.map_spans(&|s| s.start + offset..s.end + offset);
+    value.spanned(range)
+}
+
+struct Scanner<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl Scanner<'_> {
+    fn value(&mut self, value: Value) -> Value {
+        self.skip_whitespace();
+        let start = self.pos;
+        let value = match value {
+            Value::Object(entries) => {
+                self.pos += 1;
+                let entries = entries
+                    .into_iter()
+                    .map(|(k, v)| {
+                        self.skip_whitespace();
+                        let key = self.string();
+                        self.skip_whitespace();
+                        // ':'
+                        self.pos += 1;
+                        let v = self.value(v).with_key_span(key);
+                        self.skip_separator();
+                        (k, v)
+                    })
+                    .collect();
+                self.skip_whitespace();
+                self.pos += 1;
+                Value::Object(entries)
+            }
+            Value::Array(items) => {
+                self.pos += 1;
+                let items = items
+                    .into_iter()
+                    .map(|v| {
+                        let v = self.value(v);
+                        self.skip_separator();
+                        v
+                    })
+                    .collect();
+                self.skip_whitespace();
+                self.pos += 1;
+                Value::Array(items)
+            }
+            Value::String(s) => {
+                self.string();
+                Value::String(s)
+            }
+            v => {
+                while self.pos < self.bytes.len()
+                    && !matches!(self.bytes[self.pos], b',' | b']' | b'}')
+                    && !self.bytes[self.pos].is_ascii_whitespace()
+                {
+                    self.pos += 1;
+                }
+                v
+            }
+        };
+        value.spanned(start..self.pos)
+    }
+
+    fn string(&mut self) -> Span {
+        let start = self.pos;
+        self.pos += 1;
+        while self.pos < self.bytes.len() {
+            match self.bytes[self.pos] {
+                b'\\' => self.pos += 2,
+                b'"' => {
+                    self.pos += 1;
+                    break;
+                }
+                _ => self.pos += 1,
+            }
+        }
+        start..self.pos
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.pos < self.bytes.len() && self.bytes[self.pos].is_ascii_whitespace() {
+            self.pos += 1;
+        }
+    }
+
+    fn skip_separator(&mut self) {
+        self.skip_whitespace();
+        if self.bytes.get(self.pos) == Some(&b',') {
+            self.pos += 1;
+        }
+    }
+}
+
+/// Maps byte offsets in text cut out of the raw response back to the raw
+/// response.
+#[derive(Debug, Clone)]
+pub(super) struct OffsetMap {
+    // (offset in the text, offset in the raw response) where each run of
+    // text copied from the response starts.
+    runs: Vec<(usize, usize)>,
+}
+
+impl Default for OffsetMap {
+    fn default() -> Self {
+        Self { runs: vec![(0, 0)] }
+    }
+}
+
+impl OffsetMap {
+    /// Records that the text from `text_pos` on was copied from `raw_pos` on.
+    pub fn push(&mut self, text_pos: usize, raw_pos: usize) {
+        while self.runs.last().is_some_and(|&(t, _)| t >= text_pos) {
+            self.runs.pop();
+        }
+        if self.raw(text_pos) != raw_pos {
+            self.runs.push((text_pos, raw_pos));
+        }
+    }
+
+    /// Records that `range` was removed from the text.
+    pub fn remove(&mut self, range: Span) {
+        let removed = range.end - range.start;
+        let raw_end = self.raw(range.end);
+        let mut runs = self
+            .runs
+            .iter()
+            .copied()
+            .filter(|&(t, _)| t < range.start)
+            .collect::<Vec<_>>();
+        runs.push((range.start, raw_end));
+        runs.extend(
+            self.runs
+                .iter()
+                .filter(|&&(t, _)| t > range.end)
+                .map(|&(t, r)| (t - removed, r)),
+        );
+        self.runs = runs;
+    }
+
+    fn raw(&self, pos: usize) -> usize {
+        let (t, r) = self
+            .runs
+            .iter()
+            .rev()
+            .find(|&&(t, _)| t <= pos)
+            .copied()
+            .unwrap_or((0, 0));
+        r + (pos - t)
+    }
+
+    pub fn span(&self, span: Span) -> Span {
+        let start = self.raw(span.start);
+        // The end is exclusive, and may be where a removed range started.
+        let end = match span.end > span.start {
+            true => self.raw(span.end - 1) + 1,
+            false => start,
+        };
+        start..end
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use test_log::test;
+
+    /// The text of every span in `value`, and of its key, in order.
+    fn spanned_text<'a>(value: &Value, raw: &'a str, out: &mut Vec<(&'a str, Option<&'a str>)>) {
+        if let Value::Spanned(_, span, key) = value {
+            out.push((&raw[span.clone()], key.clone().map(|k| &raw[k])));
+        }
+        match value.unspanned() {
+            Value::Object(entries) => entries.iter().for_each(|(_, v)| spanned_text(v, raw, out)),
+            Value::Array(items) => items.iter().for_each(|v| spanned_text(v, raw, out)),
+            _ => {}
+        }
+    }
+
+    #[test]
+    fn locates_json_values() {
+        let raw = r#" {"a": [1, "x\"y"], "b" : {"c": null}} "#;
+        let value = locate(serde_json::from_str(raw).unwrap(), raw);
+        let mut found = vec![];
+        spanned_text(&value, raw, &mut found);
+        assert_eq!(
+            found,
+            vec![
+                (r#"{"a": [1, "x\"y"], "b" : {"c": null}}"#, None),
+                (r#"[1, "x\"y"]"#, Some(r#""a""#)),
+                ("1", None),
+                (r#""x\"y""#, None),
+                (r#"{"c": null}"#, Some(r#""b""#)),
+                ("null", Some(r#""c""#)),
+            ]
+        );
+    }
+
+    #[test]
+    fn maps_offsets_around_removed_text() {
+        // "ab<cut>cd" -> "abcd" -> "bcd". "b" ends right where "<cut>" was.
+        let mut map = OffsetMap::default();
+        map.remove(2..7);
+        map.remove(0..1);
+        assert_eq!(map.span(0..1), 1..2);
+        assert_eq!(map.span(0..3), 1..9);
+        assert_eq!(map.span(1..3), 7..9);
+    }
+}
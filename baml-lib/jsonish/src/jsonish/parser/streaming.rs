@@ -0,0 +1,721 @@
+use std::{borrow::Cow, cell::Cell};
+
+use anyhow::Result;
+use regex::Regex;
+
+use crate::jsonish::{
+    parser::{
+        entry, extraction,
+        fixing_parser::IncrementalParser,
+        markdown_parser,
+        spans::{self, OffsetMap},
+        ParsingMode,
+    },
+    value::{Fixes, Span},
+    Value,
+};
+
+use super::{ExtractionOptions, LimitExceeded, ParseLimits, ParseOptions};
+
+/// Parses a response as it streams in, without going over all of it again for
+/// every chunk like calling [`super::parse`] on the buffer would. Closed
+/// fenced blocks are parsed once, and the fixing parser resumes from the last
+/// point where nothing depends on text that hasn't arrived yet.
+///
+/// The value has the same candidates `parse` finds in the text so far, except
+/// that JSON in prose comes straight from the fixing parser instead of being
+/// grepped out first, so it isn't marked with [`Fixes::GreppedForJSON`].
+pub struct StreamingParser {
+    options: ParseOptions,
+    md_tag_start: Regex,
+    md_tag_end: Regex,
+
+    /// Everything pushed so far.
+    raw: String,
+    /// How much of `raw` has been split into `text` and stripped tags.
+    raw_pos: usize,
+    /// Which strip tag pair is open, and where it starts in `raw`.
+    open_tag: Option<(usize, usize)>,
+    stripped_tags: Vec<Fixes>,
+    /// Everything before the last answer marker.
+    preamble: String,
+    marker_scan_pos: usize,
+
+    /// `raw` without stripped tags and the preamble.
+    text: String,
+    /// Where `text` is in `raw`.
+    offsets: OffsetMap,
+    blocks: Vec<Block>,
+    open_block: Option<OpenBlock>,
+    fence_scan_pos: usize,
+    json: IncrementalParser,
+    whole_json: JsonCheck,
+    /// Set once what's pushed goes over a limit, which fails every value.
+    exceeded: Option<LimitExceeded>,
+}
+
+struct Block {
+    start: usize,
+    tag: String,
+    value: Option<Value>,
+}
+
+struct OpenBlock {
+    start: usize,
+    tag: String,
+    content_start: usize,
+    json: IncrementalParser,
+    whole_json: JsonCheck,
+}
+
+impl Default for StreamingParser {
+    fn default() -> Self {
+        Self::new(ExtractionOptions::default())
+    }
+}
+
+impl StreamingParser {
+    pub fn new(extraction: ExtractionOptions) -> Self {
+        let mut options = ParseOptions::default().with_extraction(extraction);
+        // Same as the options `parse` hands to the markdown parser.
+        options.depth = 1;
+        Self {
+            options,
+            md_tag_start: markdown_parser::md_tag_start().expect("valid regex"),
+            md_tag_end: markdown_parser::md_tag_end().expect("valid regex"),
+            raw: String::new(),
+            raw_pos: 0,
+            open_tag: None,
+            stripped_tags: vec![],
+            preamble: String::new(),
+            marker_scan_pos: 0,
+            text: String::new(),
+            offsets: Default::default(),
+            blocks: vec![],
+            open_block: None,
+            fence_scan_pos: 0,
+            json: Default::default(),
+            whole_json: Default::default(),
+            exceeded: None,
+        }
+    }
+
+    /// Same as `DeserializerOptions::limits`. The time budget is for each
+    /// value.
+    pub fn with_limits(mut self, limits: ParseLimits) -> Self {
+        self.options.limits = limits;
+        self
+    }
+
+    /// Everything pushed so far.
+    pub fn raw(&self) -> &str {
+        &self.raw
+    }
+
+    pub fn push(&mut self, chunk: &str) {
+        self.raw.push_str(chunk);
+        if let Err(e) = self.options.limits.check_input(&self.raw) {
+            self.exceeded = Some(e);
+        }
+        if self.exceeded.is_some() {
+            return;
+        }
+        self.strip_tags();
+        self.strip_preamble();
+        self.advance_blocks();
+        self.json.advance(&self.text, &self.options);
+        self.whole_json.advance(&self.text);
+    }
+
+    /// Moves `raw` into `text`, except for content between strip tags. The end
+    /// of `raw` is held back while it may be the start of a tag.
+    fn strip_tags(&mut self) {
+        let tags = &self.options.extraction.strip_tags;
+        loop {
+            let rest = &self.raw[self.raw_pos..];
+            match self.open_tag {
+                Some((pair, start)) => {
+                    let close = &tags[pair].1;
+                    match rest.find(close.as_str()).filter(|_| !close.is_empty()) {
+                        Some(idx) => {
+                            let end = self.raw_pos + idx + close.len();
+                            self.stripped_tags
+                                .push(Fixes::StrippedTag(self.raw[start..end].to_string()));
+                            self.raw_pos = end;
+                            self.open_tag = None;
+                        }
+                        None => {
+                            let resume = self.raw.len().saturating_sub(close.len());
+                            self.raw_pos = floor_char_boundary(&self.raw, resume).max(self.raw_pos);
+                            return;
+                        }
+                    }
+                }
+                None => {
+                    let next = tags
+                        .iter()
+                        .enumerate()
+                        .filter(|(_, (open, _))| !open.is_empty())
+                        .filter_map(|(pair, (open, _))| {
+                            rest.find(open.as_str()).map(|idx| (idx, pair))
+                        })
+                        .min();
+                    match next {
+                        Some((idx, pair)) => {
+                            self.offsets.push(self.text.len(), self.raw_pos);
+                            self.text.push_str(&rest[..idx]);
+                            self.open_tag = Some((pair, self.raw_pos + idx));
+                            self.raw_pos += idx + tags[pair].0.len();
+                        }
+                        None => {
+                            let held_back = tags
+                                .iter()
+                                .map(|(open, _)| partial_match_len(rest, open))
+                                .max()
+                                .unwrap_or(0);
+                            self.offsets.push(self.text.len(), self.raw_pos);
+                            self.text.push_str(&rest[..rest.len() - held_back]);
+                            self.raw_pos += rest.len() - held_back;
+                            return;
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Drops everything up to the last answer marker, and starts over on what
+    /// follows it.
+    fn strip_preamble(&mut self) {
+        let markers = &self.options.extraction.answer_markers;
+        let from = self.marker_scan_pos;
+        // Markers are ascii, so lowercasing doesn't move any byte offsets.
+        let lowercase = self.text[from..].to_ascii_lowercase();
+        let marker_end = markers
+            .iter()
+            .filter(|m| !m.is_empty())
+            .filter_map(|m| {
+                lowercase
+                    .rfind(&m.to_ascii_lowercase())
+                    .map(|idx| from + idx + m.len())
+            })
+            .max();
+        if let Some(end) = marker_end {
+            self.preamble.push_str(&self.text[..end]);
+            self.text.replace_range(..end, "");
+            self.offsets.remove(0..end);
+            self.blocks.clear();
+            self.open_block = None;
+            self.fence_scan_pos = 0;
+            self.json = Default::default();
+            self.whole_json = Default::default();
+        }
+
+        let longest = markers.iter().map(String::len).max().unwrap_or(0);
+        let resume = self.text.len().saturating_sub(longest);
+        self.marker_scan_pos = floor_char_boundary(&self.text, resume);
+    }
+
+    /// Finds fences in `text`. Fences at the very end aren't acted on, as the
+    /// next chunk may extend the tag or turn them into something else.
+    fn advance_blocks(&mut self) {
+        loop {
+            let rest = &self.text[self.fence_scan_pos..];
+            match self.open_block.take() {
+                Some(mut block) => match self.md_tag_end.find(rest) {
+                    Some(end) if end.as_str().ends_with('\n') => {
+                        let content_end = self.fence_scan_pos + end.start();
+                        let content = trimmed(&self.text, block.content_start..content_end);
+                        let value = entry::parse(
+                            &self.text[content.clone()],
+                            self.options.next_from_mode(ParsingMode::JsonMarkdown),
+                        )
+                        .map(|v| spans::nested(v, content));
+                        if let Err(e) = &value {
+                            log::debug!("Error parsing markdown block: {:?}", e);
+                            if let Some(e) = e.downcast_ref::<LimitExceeded>() {
+                                self.exceeded = Some(e.clone());
+                            }
+                        }
+                        self.blocks.push(Block {
+                            start: block.start,
+                            tag: block.tag,
+                            value: value.ok(),
+                        });
+                        self.fence_scan_pos += end.end();
+                    }
+                    _ => {
+                        let resume = self.text.len().saturating_sub(3);
+                        self.fence_scan_pos =
+                            floor_char_boundary(&self.text, resume).max(self.fence_scan_pos);
+                        let content = &self.text[block.content_start..];
+                        block.json.advance(content, &self.options);
+                        block.whole_json.advance(content);
+                        self.open_block = Some(block);
+                        return;
+                    }
+                },
+                None => match self.md_tag_start.find(rest) {
+                    Some(start) if start.as_str().ends_with('\n') => {
+                        let content_start = self.fence_scan_pos + start.end();
+                        self.open_block = Some(OpenBlock {
+                            start: self.fence_scan_pos + start.start(),
+                            tag: markdown_parser::tag_name(start.as_str()),
+                            content_start,
+                            json: Default::default(),
+                            whole_json: Default::default(),
+                        });
+                        self.fence_scan_pos = content_start;
+                    }
+                    _ => {
+                        // Only a fence followed by nothing but its tag so far
+                        // can still become an opening fence.
+                        let resume = match rest.rfind("```") {
+                            Some(idx)
+                                if rest[idx + 3..]
+                                    .chars()
+                                    .all(|c| c.is_ascii_alphanumeric() || c == ' ') =>
+                            {
+                                self.fence_scan_pos + idx
+                            }
+                            _ => floor_char_boundary(&self.text, self.text.len().saturating_sub(2)),
+                        };
+                        self.fence_scan_pos = resume.max(self.fence_scan_pos);
+                        return;
+                    }
+                },
+            }
+        }
+    }
+
+    /// The candidates for everything pushed so far.
+    pub(crate) fn value(&self) -> Result<Value> {
+        if let Some(e) = &self.exceeded {
+            return Err(e.clone().into());
+        }
+        let mut options = self.options.clone();
+        options.deadline = options.limits.deadline();
+
+        let mut discarded = self.stripped_tags.clone();
+        let mut offsets = self.offsets.clone();
+        let text = match self.open_tag {
+            Some((_, start)) => {
+                discarded.push(Fixes::StrippedTag(self.raw[start..].to_string()));
+                Cow::Borrowed(self.text.as_str())
+            }
+            // Not a tag after all, at least for now.
+            None if self.raw_pos < self.raw.len() => {
+                offsets.push(self.text.len(), self.raw_pos);
+                Cow::Owned(format!("{}{}", self.text, &self.raw[self.raw_pos..]))
+            }
+            None => Cow::Borrowed(self.text.as_str()),
+        };
+        if !self.preamble.is_empty() {
+            discarded.push(Fixes::StrippedPreamble(self.preamble.clone()));
+        }
+
+        if discarded.is_empty() {
+            let mut value = self.candidates(&text, 0..text.len(), &mut discarded, &options)?;
+            options.limits.check_candidates(&value)?;
+            value.map_spans(&|s| offsets.span(s));
+            return Ok(value);
+        }
+        if text.trim().is_empty() {
+            // Nothing but stripped content so far.
+            return Ok(Value::String(String::new()));
+        }
+        let label = trimmed(&text, 0..text.len());
+        let mut value = self.candidates(&text, label, &mut discarded, &options)?;
+        options.limits.check_candidates(&value)?;
+        value.map_spans(&|s| offsets.span(s));
+        Ok(extraction::annotate(value, &discarded))
+    }
+
+    /// Mirrors `parse`, with the `label` range of `text` standing for the text
+    /// in the candidates.
+    fn candidates(
+        &self,
+        text: &str,
+        label: Span,
+        discarded: &mut Vec<Fixes>,
+        options: &ParseOptions,
+    ) -> Result<Value> {
+        if let Some(v) = self.whole_json.parse(text) {
+            options.limits.check_value(&v, 0)?;
+            return Ok(Value::AnyOf(
+                vec![spans::locate(v, text)],
+                text[label].to_string(),
+            ));
+        }
+
+        let mut blocks = self
+            .blocks
+            .iter()
+            .map(|b| Ok((b.start, b.tag.clone(), b.value.clone())))
+            .chain(self.open_block.as_ref().map(|b| {
+                let value = self.open_block_value(b, text, options)?;
+                Ok((b.start, b.tag.clone(), value))
+            }))
+            .collect::<Result<Vec<_>>>()?;
+        let mut label = label;
+        if self.options.extraction.prefer_last_fenced_block && blocks.len() > 1 {
+            let last = blocks.pop().unwrap();
+            discarded.push(Fixes::StrippedFencedBlocks(text[..last.0].to_string()));
+            label = trimmed(text, last.0..text.len());
+            blocks = vec![last];
+        }
+        let items = blocks
+            .into_iter()
+            .filter_map(|(_, tag, value)| Some((tag, value?)))
+            .collect::<Vec<_>>();
+        if let Some(v) = entry::from_markdown(items, &text[label.clone()]) {
+            return Ok(v);
+        }
+
+        // `parse` gets the extracted text trimmed. What's trimmed comes after
+        // the last checkpoint, so it can be left out here.
+        match self.json.parse(&text[..label.end], options) {
+            Ok(items) => {
+                if let Some(v) = entry::from_fixed_json(items, &text[label.clone()]) {
+                    return Ok(v);
+                }
+            }
+            Err(e) => {
+                log::debug!("Error fixing json: {:?}", e);
+                if e.is::<LimitExceeded>() {
+                    return Err(e);
+                }
+            }
+        }
+
+        Ok(Value::String(text[label.clone()].to_string()).spanned(label))
+    }
+
+    fn open_block_value(
+        &self,
+        block: &OpenBlock,
+        text: &str,
+        options: &ParseOptions,
+    ) -> Result<Option<Value>> {
+        // A fence at the very end closes the block, unless more text follows.
+        if let Some(end) = self.md_tag_end.find(&text[self.fence_scan_pos..]) {
+            let content_end = self.fence_scan_pos + end.start();
+            let content = trimmed(text, block.content_start..content_end);
+            let options = options.next_from_mode(ParsingMode::JsonMarkdown);
+            return match entry::parse(&text[content.clone()], options) {
+                Ok(v) => Ok(Some(spans::nested(v, content))),
+                Err(e) if e.is::<LimitExceeded>() => Err(e),
+                Err(_) => Ok(None),
+            };
+        }
+
+        let content = trimmed(text, block.content_start..text.len());
+        let trimmed = &text[content.clone()];
+        if let Some(v) = block.whole_json.parse(&text[block.content_start..]) {
+            options.limits.check_value(&v, 0)?;
+            let v = Value::AnyOf(vec![spans::locate(v, trimmed)], trimmed.to_string());
+            return Ok(Some(spans::nested(v, content)));
+        }
+        // Trimmed like in `parse`, see `candidates`.
+        match block
+            .json
+            .parse(&text[block.content_start..content.end], options)
+        {
+            // The fixing parser's spans start from the untrimmed content.
+            Ok(items) => Ok(entry::from_fixed_json(items, trimmed).map(|mut v| {
+                let offset = block.content_start;
+                v.map_spans(&|s| s.start + offset..s.end + offset);
+                v.spanned(content)
+            })),
+            Err(e) if e.is::<LimitExceeded>() => Err(e),
+            Err(e) => {
+                log::debug!("Error fixing json: {:?}", e);
+                Ok(None)
+            }
+        }
+    }
+}
+
+/// Whether text that only grows can still be JSON by itself, which is what
+/// `parse` tries first. Tracked as the text grows, so that `serde_json` isn't
+/// run over all of it for every value while it can't succeed.
+#[derive(Default)]
+struct JsonCheck {
+    brackets: Brackets,
+    /// Set once `serde_json` failed on something in the text advanced over,
+    /// which makes it fail on any text that starts the same way.
+    failed: Cell<bool>,
+}
+
+impl JsonCheck {
+    /// `str` is all the text so far, and starts with the text seen before.
+    fn advance(&mut self, str: &str) {
+        self.brackets.scan(str);
+    }
+
+    /// `serde_json::from_str(str)`, unless it can't succeed. `str` starts with
+    /// the text advanced over.
+    fn parse(&self, str: &str) -> Option<Value> {
+        if self.failed.get() {
+            return None;
+        }
+        let mut brackets = self.brackets;
+        brackets.scan(str);
+        if !brackets.balanced() {
+            return None;
+        }
+        match serde_json::from_str(str) {
+            Ok(v) => Some(v),
+            Err(e) => {
+                // Running out of text isn't final, and neither is anything
+                // that hasn't been advanced over.
+                if e.classify() == serde_json::error::Category::Syntax
+                    && error_end(str, &e) <= self.brackets.scanned
+                {
+                    self.failed.set(true);
+                }
+                None
+            }
+        }
+    }
+}
+
+/// The nesting of brackets outside of strings, which must be balanced for
+/// the text to be JSON.
+#[derive(Clone, Copy, Default)]
+struct Brackets {
+    scanned: usize,
+    depth: usize,
+    /// A bracket was closed that wasn't open.
+    unbalanced: bool,
+    in_string: bool,
+    escaped: bool,
+}
+
+impl Brackets {
+    /// Scans what follows the text scanned before in `str`.
+    fn scan(&mut self, str: &str) {
+        for b in str[self.scanned..].bytes() {
+            match b {
+                _ if self.escaped => self.escaped = false,
+                b'\\' if self.in_string => self.escaped = true,
+                b'"' => self.in_string = !self.in_string,
+                _ if self.in_string => {}
+                b'{' | b'[' => self.depth += 1,
+                b'}' | b']' => match self.depth.checked_sub(1) {
+                    Some(depth) => self.depth = depth,
+                    None => self.unbalanced = true,
+                },
+                _ => {}
+            }
+        }
+        self.scanned = str.len();
+    }
+
+    fn balanced(&self) -> bool {
+        !self.unbalanced && !self.in_string && self.depth == 0
+    }
+}
+
+/// Where the character `serde_json` failed on ends in `str`, or a little past
+/// it for a multibyte character.
+fn error_end(str: &str, e: &serde_json::Error) -> usize {
+    let line_start = str
+        .split_inclusive('\n')
+        .take(e.line().saturating_sub(1))
+        .map(str::len)
+        .sum::<usize>();
+    line_start + e.column()
+}
+
+/// `range` of `str` without surrounding whitespace.
+fn trimmed(str: &str, range: Span) -> Span {
+    let content = &str[range.clone()];
+    let start = range.start + (content.len() - content.trim_start().len());
+    start..start + content.trim().len()
+}
+
+/// The length of the longest suffix of `str` that `tag` starts with.
+fn partial_match_len(str: &str, tag: &str) -> usize {
+    (1..tag.len())
+        .rev()
+        .find(|&len| tag.is_char_boundary(len) && str.ends_with(&tag[..len]))
+        .unwrap_or(0)
+}
+
+fn floor_char_boundary(str: &str, mut idx: usize) -> usize {
+    while !str.is_char_boundary(idx) {
+        idx -= 1;
+    }
+    idx
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::DeserializerOptions;
+    use baml_types::{BamlValue, FieldType, TypeValue};
+    use internal_baml_jinja::types::{Class, Name, OutputFormatContent};
+    use test_log::test;
+
+    fn person() -> (OutputFormatContent, FieldType) {
+        let string = FieldType::Primitive(TypeValue::String);
+        let class = Class {
+            name: Name::new("Person".into()),
+            fields: vec![
+                (Name::new("name".into()), string.clone(), None),
+                (
+                    Name::new("tags".into()),
+                    FieldType::List(Box::new(string)),
+                    None,
+                ),
+            ],
+            extra_fields: None,
+            discriminator: None,
+        };
+        let target = FieldType::Class("Person".into());
+        (
+            OutputFormatContent::new(vec![], vec![class], target.clone()),
+            target,
+        )
+    }
+
+    /// Pushes `raw` a character at a time, and checks that every prefix that
+    /// is longer than `skip` coerces to the same thing as parsing it in one go.
+    fn assert_matches_parse(raw: &str, skip: usize, options: &DeserializerOptions) {
+        assert_stream_matches(raw, skip, options, BamlValue::from);
+    }
+
+    fn assert_stream_matches<T: PartialEq + std::fmt::Debug>(
+        raw: &str,
+        skip: usize,
+        options: &DeserializerOptions,
+        f: impl Fn(crate::BamlValueWithFlags) -> T,
+    ) {
+        let (of, target) = person();
+        let mut stream = StreamingParser::new(options.extraction.clone());
+        for (idx, c) in raw.char_indices() {
+            stream.push(&c.to_string());
+            let prefix = &raw[..idx + c.len_utf8()];
+            if prefix.len() <= skip {
+                continue;
+            }
+            let expected = crate::from_str_with_options(&of, &target, prefix, true, options)
+                .ok()
+                .map(&f);
+            let actual = crate::from_stream(&of, &target, &stream, true, options)
+                .ok()
+                .map(&f);
+            assert_eq!(actual, expected, "after {:?}", prefix);
+        }
+    }
+
+    #[test]
+    fn plain_json() {
+        assert_matches_parse(
+            r#"{"name": "Ann", "tags": ["a", "b, c"]}"#,
+            0,
+            &Default::default(),
+        );
+    }
+
+    #[test]
+    fn fixed_json() {
+        assert_matches_parse(
+            "{name: Ann Lee, 'tags': [a, \"\"\"b\"\"\", c], // done\n}",
+            0,
+            &Default::default(),
+        );
+    }
+
+    #[test]
+    fn markdown() {
+        assert_matches_parse(
+            "Sure!\n```json\n{\"name\": \"Ann\", \"tags\": [\"a\"]}\n```\nor\n```json\n{\"name\": \"Bob\"}\n```\n",
+            0,
+            &Default::default(),
+        );
+    }
+
+    #[test]
+    fn stripped_tags_and_preamble() {
+        let options = DeserializerOptions {
+            extraction: ExtractionOptions {
+                strip_tags: vec![("<think>".into(), "</think>".into())],
+                answer_markers: vec!["Answer:".into()],
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let thinking = r#"<think>{"name": "Draft"}</think>"#;
+        // Unlike `parse`, the stripped content isn't parsed while it's all
+        // there is.
+        let mut stream = StreamingParser::new(options.extraction.clone());
+        stream.push(&thinking[..20]);
+        assert_eq!(stream.value().unwrap(), Value::String(String::new()));
+
+        assert_matches_parse(
+            &format!(r#"{thinking} ok. answer: {{"name": "Ann", "tags": ["x"]}}"#),
+            thinking.len() + 1,
+            &options,
+        );
+    }
+
+    #[test]
+    fn chunking_does_not_matter() {
+        let raw =
+            "Here:\n```json\n{\"name\": Ann, \"tags\": [\"a\", b]}\n```\nand {\"name\": \"Bob\"}";
+        let mut whole = StreamingParser::default();
+        whole.push(raw);
+        let expected = whole.value().unwrap();
+        for split in (0..=raw.len()).filter(|i| raw.is_char_boundary(*i)) {
+            let mut stream = StreamingParser::default();
+            stream.push(&raw[..split]);
+            stream.push(&raw[split..]);
+            assert_eq!(stream.value().unwrap(), expected, "split at {split}");
+        }
+    }
+
+    #[test]
+    fn json_check() {
+        let mut check = JsonCheck::default();
+        let mut pushed = String::new();
+        let mut push = |check: &mut JsonCheck, chunk: &str| {
+            pushed.push_str(chunk);
+            check.advance(&pushed);
+            pushed.clone()
+        };
+
+        // Brackets in strings don't count.
+        let text = push(&mut check, r#"{"a": "}\"]"#);
+        assert!(check.parse(&text).is_none());
+        let text = push(&mut check, r#"", "b": [1]}"#);
+        assert!(check.parse(&text).is_some());
+
+        // Only what was advanced over fails for good.
+        assert!(check.parse(&format!("{text} <")).is_none());
+        assert!(!check.failed.get());
+        let text = push(&mut check, " ok");
+        assert!(check.parse(&text).is_none());
+        assert!(check.failed.get());
+    }
+
+    #[test]
+    fn spans_match_parse() {
+        let thinking = "<think>{name: Bob}</think>";
+        for (raw, skip) in [
+            (r#"{"name": "Ann", "tags": ["a", "b, c"]}"#.to_string(), 0),
+            (
+                format!("{thinking}{{name: Ann Lee, 'tags': [a, \"\"\"b\"\"\", c], // done\n}}"),
+                // Same as in `stripped_tags_and_preamble`.
+                thinking.len(),
+            ),
+            (
+                "Sure!\n```json\n{\"name\": \"Ann\", \"tags\": [\"a\"]}\n```\nor\n```json\n{\"name\": \"Bob\"}\n```\n".to_string(),
+                0,
+            ),
+        ] {
+            assert_stream_matches(&raw, skip, &Default::default(), |v| v.sources());
+        }
+    }
+}
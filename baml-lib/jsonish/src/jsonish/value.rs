@@ -1,14 +1,41 @@
-use std::collections::HashSet;
+use std::{collections::HashSet, ops::Range};
 
-use baml_types::BamlMap;
+/// Byte offsets into the raw response.
+pub type Span = Range<usize>;
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Fixes {
     GreppedForJSON,
     InferredArray,
+
+    // Python literals
+    PythonConstant(String),
+    TupleToArray,
+    TripleQuotedString,
+
+    // JSON5 literals
+    HexNumber(String),
+    NonFiniteNumber(String),
+    SignedNumber(String),
+
+    // Text discarded before parsing
+    StrippedTag(String),
+    StrippedPreamble(String),
+    StrippedFencedBlocks(String),
+
+    // The input ended before the value was closed.
+    Truncated(OpenCollection),
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+/// What a value was cut off inside of when the input ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum OpenCollection {
+    String,
+    Array,
+    Object,
+}
+
+#[derive(Debug, Clone)]
 pub enum Value {
     // Primitive Types
     String(String),
@@ -17,18 +44,24 @@ pub enum Value {
     Null,
 
     // Complex Types
-    Object(BamlMap<String, Value>),
+    // Keys may repeat, see `DuplicateKeyPolicy`.
+    Object(Vec<(String, Value)>),
     Array(Vec<Value>),
 
     // Fixed types
     Markdown(String, Box<Value>),
     FixedJson(Box<Value>, Vec<Fixes>),
     AnyOf(Vec<Value>, String),
+
+    // Where the value was found, and its key's span for object entries.
+    // Ignored when comparing values.
+    Spanned(Box<Value>, Span, Option<Span>),
 }
 
 impl Value {
     pub fn r#type(&self) -> String {
         match self {
+            Value::Spanned(v, _, _) => v.r#type(),
             Value::String(_) => "String".to_string(),
             Value::Number(_) => "Number".to_string(),
             Value::Boolean(_) => "Boolean".to_string(),
@@ -76,6 +109,7 @@ impl Value {
 impl std::fmt::Display for Value {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
+            Value::Spanned(v, _, _) => write!(f, "{}", v),
             Value::String(s) => write!(f, "{}", s),
             Value::Number(n) => write!(f, "{}", n),
             Value::Boolean(b) => write!(f, "{}", b),
@@ -113,30 +147,216 @@ impl std::fmt::Display for Value {
     }
 }
 
+impl Value {
+    /// The value under any spans.
+    pub fn unspanned(&self) -> &Value {
+        match self {
+            Value::Spanned(v, _, _) => v.unspanned(),
+            v => v,
+        }
+    }
+
+    /// Where the value is in the response, if it came from it.
+    pub(crate) fn span(&self) -> Option<&Span> {
+        match self {
+            Value::Spanned(_, span, _) => Some(span),
+            _ => None,
+        }
+    }
+
+    pub(super) fn spanned(self, span: Span) -> Value {
+        Value::Spanned(Box::new(self), span, None)
+    }
+
+    /// Sets the span of the key this value was found under.
+    pub(super) fn with_key_span(self, key: Span) -> Value {
+        match self {
+            Value::Spanned(v, span, _) => Value::Spanned(v, span, Some(key)),
+            v => v,
+        }
+    }
+
+    /// Moves every span, e.g. when the value was parsed from a slice of the
+    /// response.
+    pub(super) fn map_spans(&mut self, f: &impl Fn(Span) -> Span) {
+        match self {
+            Value::String(_) | Value::Number(_) | Value::Boolean(_) | Value::Null => {}
+            Value::Object(entries) => entries.iter_mut().for_each(|(_, v)| v.map_spans(f)),
+            Value::Array(items) | Value::AnyOf(items, _) => {
+                items.iter_mut().for_each(|v| v.map_spans(f))
+            }
+            Value::Markdown(_, v) | Value::FixedJson(v, _) => v.map_spans(f),
+            Value::Spanned(v, span, key) => {
+                v.map_spans(f);
+                *span = f(span.clone());
+                *key = key.take().map(f);
+            }
+        }
+    }
+
+    pub(crate) fn without_spans(&self) -> Value {
+        match self.unspanned() {
+            Value::Object(entries) => Value::Object(
+                entries
+                    .iter()
+                    .map(|(k, v)| (k.clone(), v.without_spans()))
+                    .collect(),
+            ),
+            Value::Array(items) => Value::Array(items.iter().map(Value::without_spans).collect()),
+            Value::AnyOf(items, s) => {
+                Value::AnyOf(items.iter().map(Value::without_spans).collect(), s.clone())
+            }
+            Value::Markdown(tag, v) => Value::Markdown(tag.clone(), v.without_spans().into()),
+            Value::FixedJson(v, fixes) => Value::FixedJson(v.without_spans().into(), fixes.clone()),
+            v => v.clone(),
+        }
+    }
+}
+
+impl PartialEq for Value {
+    fn eq(&self, other: &Self) -> bool {
+        match (self.unspanned(), other.unspanned()) {
+            (Value::String(a), Value::String(b)) => a == b,
+            (Value::Number(a), Value::Number(b)) => a == b,
+            (Value::Boolean(a), Value::Boolean(b)) => a == b,
+            (Value::Null, Value::Null) => true,
+            (Value::Object(a), Value::Object(b)) => a == b,
+            (Value::Array(a), Value::Array(b)) => a == b,
+            (Value::Markdown(a_tag, a), Value::Markdown(b_tag, b)) => a_tag == b_tag && a == b,
+            (Value::FixedJson(a, a_fixes), Value::FixedJson(b, b_fixes)) => {
+                a_fixes == b_fixes && a == b
+            }
+            (Value::AnyOf(a, a_str), Value::AnyOf(b, b_str)) => a_str == b_str && a == b,
+            _ => false,
+        }
+    }
+}
+
+impl Eq for Value {}
+
+impl From<&Value> for serde_json::Value {
+    fn from(value: &Value) -> Self {
+        match value {
+            Value::String(s) => serde_json::Value::String(s.clone()),
+            Value::Number(n) => serde_json::Value::Number(n.clone()),
+            Value::Boolean(b) => serde_json::Value::Bool(*b),
+            Value::Null => serde_json::Value::Null,
+            // Repeated keys: last wins.
+            Value::Object(o) => {
+                serde_json::Value::Object(o.iter().map(|(k, v)| (k.clone(), v.into())).collect())
+            }
+            Value::Array(a) => serde_json::Value::Array(a.iter().map(Into::into).collect()),
+            Value::Markdown(_, v) | Value::FixedJson(v, _) | Value::Spanned(v, _, _) => {
+                v.as_ref().into()
+            }
+            Value::AnyOf(_, s) => serde_json::Value::String(s.clone()),
+        }
+    }
+}
+
 impl<'de> serde::Deserialize<'de> for Value {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where
         D: serde::Deserializer<'de>,
     {
-        match serde_json::Value::deserialize(deserializer)? {
-            serde_json::Value::String(s) => Ok(Value::String(s)),
-            serde_json::Value::Number(n) => Ok(Value::Number(n)),
-            serde_json::Value::Bool(b) => Ok(Value::Boolean(b)),
-            serde_json::Value::Null => Ok(Value::Null),
-            serde_json::Value::Object(o) => {
-                let mut map = BamlMap::new();
-                for (k, v) in o {
-                    map.insert(k, serde_json::from_value(v).unwrap());
-                }
-                Ok(Value::Object(map))
+        // Visited directly (not via serde_json::Value) so repeated keys are kept.
+        struct ValueVisitor;
+
+        impl<'de> serde::de::Visitor<'de> for ValueVisitor {
+            type Value = Value;
+
+            fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                f.write_str("any JSON value")
+            }
+
+            fn visit_bool<E>(self, b: bool) -> Result<Value, E> {
+                Ok(Value::Boolean(b))
+            }
+
+            fn visit_i64<E>(self, n: i64) -> Result<Value, E> {
+                Ok(Value::Number(n.into()))
+            }
+
+            fn visit_u64<E>(self, n: u64) -> Result<Value, E> {
+                Ok(Value::Number(n.into()))
+            }
+
+            fn visit_f64<E>(self, n: f64) -> Result<Value, E> {
+                Ok(serde_json::Number::from_f64(n).map_or(Value::Null, Value::Number))
             }
-            serde_json::Value::Array(a) => {
+
+            fn visit_str<E>(self, s: &str) -> Result<Value, E> {
+                Ok(Value::String(s.into()))
+            }
+
+            fn visit_string<E>(self, s: String) -> Result<Value, E> {
+                Ok(Value::String(s))
+            }
+
+            fn visit_unit<E>(self) -> Result<Value, E> {
+                Ok(Value::Null)
+            }
+
+            fn visit_none<E>(self) -> Result<Value, E> {
+                Ok(Value::Null)
+            }
+
+            fn visit_some<D>(self, deserializer: D) -> Result<Value, D::Error>
+            where
+                D: serde::Deserializer<'de>,
+            {
+                serde::Deserialize::deserialize(deserializer)
+            }
+
+            fn visit_seq<A>(self, mut seq: A) -> Result<Value, A::Error>
+            where
+                A: serde::de::SeqAccess<'de>,
+            {
                 let mut vec = Vec::new();
-                for v in a {
-                    vec.push(serde_json::from_value(v).unwrap());
+                while let Some(v) = seq.next_element()? {
+                    vec.push(v);
                 }
                 Ok(Value::Array(vec))
             }
+
+            fn visit_map<A>(self, mut map: A) -> Result<Value, A::Error>
+            where
+                A: serde::de::MapAccess<'de>,
+            {
+                let mut entries = Vec::new();
+                while let Some(entry) = map.next_entry::<String, Value>()? {
+                    entries.push(entry);
+                }
+                Ok(Value::Object(entries))
+            }
         }
+
+        deserializer.deserialize_any(ValueVisitor)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use test_log::test;
+
+    #[test]
+    fn keeps_repeated_keys() {
+        let value: Value =
+            serde_json::from_str(r#"{"a": 1, "b": [true, null], "a": 2.5}"#).unwrap();
+        assert_eq!(
+            value,
+            Value::Object(vec![
+                ("a".into(), Value::Number(1.into())),
+                (
+                    "b".into(),
+                    Value::Array(vec![Value::Boolean(true), Value::Null])
+                ),
+                (
+                    "a".into(),
+                    Value::Number(serde_json::Number::from_f64(2.5).unwrap())
+                ),
+            ])
+        );
     }
 }
@@ -1,31 +1,160 @@
 use anyhow::Result;
 mod deserializer;
+#[cfg(any(test, feature = "fuzzing"))]
+pub mod fuzzing;
 mod jsonish;
 
 pub use baml_types::FieldType;
 use deserializer::coercer::{ParsingContext, TypeCoercer};
 
-pub use deserializer::types::BamlValueWithFlags;
+pub use deserializer::alternatives::{Alternative, Alternatives};
+pub use deserializer::coercer::{CoercionError, ParsingError, ParsingErrorKind};
+pub use deserializer::deserialize_options::{DeserializerOptions, DuplicateKeyPolicy};
+pub use deserializer::explain::{Candidate, Explanation, Outcome, Pick};
+pub use deserializer::repair::repair_prompt;
+pub use deserializer::score::{FlagScore, ScoringPolicy};
+pub use deserializer::types::{BamlValueWithFlags, CompletionState, ValueSource};
 use internal_baml_core::ir::TypeValue;
 pub use internal_baml_jinja::types::OutputFormatContent;
+pub use jsonish::{ExtractionOptions, LimitExceeded, OpenCollection, ParseLimits, StreamingParser};
 
 pub fn from_str(
     of: &OutputFormatContent,
     target: &FieldType,
     raw_string: &str,
     allow_partials: bool,
+) -> Result<BamlValueWithFlags> {
+    from_str_with_options(
+        of,
+        target,
+        raw_string,
+        allow_partials,
+        &DeserializerOptions::default(),
+    )
+}
+
+pub fn from_str_with_options(
+    of: &OutputFormatContent,
+    target: &FieldType,
+    raw_string: &str,
+    allow_partials: bool,
+    options: &DeserializerOptions,
 ) -> Result<BamlValueWithFlags> {
     if matches!(target, FieldType::Primitive(TypeValue::String)) {
-        return Ok(BamlValueWithFlags::String(raw_string.to_string().into()));
+        return Ok(raw_string_value(raw_string));
     }
 
     // When the schema is just a string, i should really just return the raw_string w/o parsing it.
-    let value = jsonish::parse(raw_string, jsonish::ParseOptions::default())?;
+    let deadline = options.limits.deadline();
+    let value = parse(raw_string, options, deadline)?;
+    let ctx = ParsingContext::new(of, options, allow_partials, deadline);
+    coerce(&ctx, target, value, raw_string.len(), deadline)
+}
+
+/// Up to `k` of the ways to read `raw_string` as `target` that coerce to
+/// different values, best first. The best one is what
+/// [`from_str_with_options`] returns, with every candidate kept in its
+/// `FirstMatch` flags. The others each make one choice differently, e.g.
+/// which JSON object in the response to use, or which option of a union a
+/// field is. Slower, as it keeps a copy of every candidate.
+pub fn alternatives_from_str(
+    of: &OutputFormatContent,
+    target: &FieldType,
+    raw_string: &str,
+    allow_partials: bool,
+    options: &DeserializerOptions,
+    k: usize,
+) -> Result<Alternatives> {
+    if matches!(target, FieldType::Primitive(TypeValue::String)) {
+        let value = raw_string_value(raw_string);
+        return Ok(deserializer::alternatives::rank(value, k, &options.scoring));
+    }
+
+    let options = DeserializerOptions {
+        keep_alternatives: true,
+        ..options.clone()
+    };
+    let deadline = options.limits.deadline();
+    let value = parse(raw_string, &options, deadline)?;
+    let ctx = ParsingContext::new(of, &options, allow_partials, deadline);
+    let picked = coerce(&ctx, target, value, raw_string.len(), deadline)?;
+    Ok(deserializer::alternatives::rank(
+        picked,
+        k,
+        &options.scoring,
+    ))
+}
+
+/// Same as [`from_str_with_options`], with every choice made along the way
+/// and why each candidate lost: its score breakdown if it coerced, its error
+/// if not. When nothing coerces, the error already has every candidate's.
+/// Slower, as it keeps a copy of every candidate.
+pub fn explain_from_str(
+    of: &OutputFormatContent,
+    target: &FieldType,
+    raw_string: &str,
+    allow_partials: bool,
+    options: &DeserializerOptions,
+) -> Result<Explanation> {
+    let options = DeserializerOptions {
+        keep_alternatives: true,
+        ..options.clone()
+    };
+    let value = from_str_with_options(of, target, raw_string, allow_partials, &options)?;
+    Ok(Explanation::new(value, &options.scoring))
+}
+
+/// Like [`from_str_with_options`] on everything pushed to `stream` so far.
+/// Create the parser with the same `options.extraction` and `options.limits`.
+pub fn from_stream(
+    of: &OutputFormatContent,
+    target: &FieldType,
+    stream: &StreamingParser,
+    allow_partials: bool,
+    options: &DeserializerOptions,
+) -> Result<BamlValueWithFlags> {
+    if matches!(target, FieldType::Primitive(TypeValue::String)) {
+        return Ok(raw_string_value(stream.raw()));
+    }
+
+    let deadline = options.limits.deadline();
+    let value = stream.value()?;
+    let ctx = ParsingContext::new(of, options, allow_partials, deadline);
+    coerce(&ctx, target, value, stream.raw().len(), deadline)
+}
+
+fn raw_string_value(raw_string: &str) -> BamlValueWithFlags {
+    BamlValueWithFlags::String(raw_string.to_string().into()).with_span(0..raw_string.len())
+}
+
+fn parse(
+    raw_string: &str,
+    options: &DeserializerOptions,
+    deadline: jsonish::Deadline,
+) -> Result<jsonish::Value> {
+    jsonish::parse(
+        raw_string,
+        jsonish::ParseOptions::default()
+            .with_extraction(options.extraction.clone())
+            .with_limits(options.limits.clone(), deadline),
+    )
+}
+
+/// `value` was parsed from a response `raw_len` bytes long.
+fn coerce(
+    ctx: &ParsingContext,
+    target: &FieldType,
+    value: jsonish::Value,
+    raw_len: usize,
+    deadline: jsonish::Deadline,
+) -> Result<BamlValueWithFlags> {
+    // Values with no span of their own were derived from the whole response.
+    let value = jsonish::Value::Spanned(Box::new(value), 0..raw_len, None);
+
     // let schema = deserializer::schema::from_jsonish_value(&value, None);
 
     // Pick the schema that is the most specific.
     // log::info!("Parsed: {}", schema);
-    let ctx = ParsingContext::new(of, allow_partials);
     // let res = schema.cast_to(target);
     // log::info!("Casted: {:?}", res);
 
@@ -37,8 +166,111 @@ pub fn from_str(
     // Determine the best way to get the desired schema from the parsed schema.
 
     // Lets try to now coerce the value into the expected schema.
-    match target.coerce(&ctx, target, Some(&value)) {
+    let res = target.coerce(ctx, target, Some(&value));
+    // Running out of time may have only failed some of the candidates.
+    deadline.check()?;
+    match res {
         Ok(v) => Ok(v),
-        Err(e) => anyhow::bail!("Failed to coerce value: {}", e),
+        Err(e) => Err(CoercionError(e).into()),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use test_log::test;
+
+    /// Mutates a few seed responses into malformed ones. Not a replacement for
+    /// running the fuzz targets, but keeps the obvious panics out.
+    fn mutations(count: usize) -> Vec<String> {
+        const SEEDS: &[&str] = &[
+            r#"{"name": "a", "tags": ["x", "y"], "size": 1.5}"#,
+            "<think>{\"name\": 1}</think>\n```json\n[{name: 'b', tags: (1, 2)}]\n```",
+            "Blue, or maybe {\"name\": \"\"\"c\"\"\"} // done\n/* end */",
+            "é😀 ```\n{\"name\": \"d\\\"\", \"size\": NaN}\n``` [True, None]",
+        ];
+        const TOKENS: &[&str] = &[
+            "{",
+            "}",
+            "[",
+            "]",
+            "(",
+            ")",
+            "\"",
+            "'",
+            "\"\"\"",
+            "`",
+            "```",
+            "```json\n",
+            ":",
+            ",",
+            "\\",
+            "//",
+            "/*",
+            "*/",
+            "#",
+            "\n",
+            " ",
+            "é",
+            "😀",
+            "<think>",
+            "</think>",
+            "null",
+            "0x",
+            "-",
+            "+",
+            ".",
+            "e9",
+        ];
+
+        // xorshift, so failures reproduce.
+        let mut state = 0x2545_f491_4f6c_dd1du64;
+        let mut next = |n: usize| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            (state % n as u64) as usize
+        };
+        (0..count)
+            .map(|_| {
+                let mut s = SEEDS[next(SEEDS.len())].to_string();
+                for _ in 0..1 + next(6) {
+                    let boundaries = (0..=s.len())
+                        .filter(|&i| s.is_char_boundary(i))
+                        .collect::<Vec<_>>();
+                    let at = boundaries[next(boundaries.len())];
+                    let end = boundaries.iter().copied().find(|&i| i > at).unwrap_or(at);
+                    match next(3) {
+                        0 => s.insert_str(at, TOKENS[next(TOKENS.len())]),
+                        1 => s.replace_range(at..end, ""),
+                        _ => s.truncate(at),
+                    }
+                }
+                s
+            })
+            .collect()
+    }
+
+    #[test]
+    fn malformed_input_never_panics() {
+        let (of, targets) = fuzzing::schema();
+        let edge_cases = [
+            "}".into(),
+            "`".into(),
+            "[".repeat(10_000),
+            "{\"a\":".repeat(10_000),
+        ];
+        for raw in mutations(200).into_iter().chain(edge_cases) {
+            let _ = jsonish::parse(&raw, Default::default());
+            for target in &targets {
+                let _ = from_str(&of, target, &raw, true);
+                let _ = from_str(&of, target, &raw, false);
+            }
+            let mut stream = StreamingParser::default();
+            for chunk in raw.as_bytes().chunks(7) {
+                stream.push(&String::from_utf8_lossy(chunk));
+            }
+            let _ = from_stream(&of, &targets[0], &stream, true, &Default::default());
+        }
     }
 }
@@ -0,0 +1,37 @@
+use internal_baml_diagnostics::DatamodelError;
+use internal_baml_schema_ast::ast::{Class, WithName};
+
+use crate::{coerce, context::Context, types::ClassAttributes};
+
+pub(super) fn visit_discriminator_attribute<'db>(
+    attributes: &mut ClassAttributes,
+    ast_class: &'db Class,
+    ctx: &mut Context<'db>,
+) {
+    let (key, span) = match ctx
+        .visit_default_arg_with_idx("key")
+        .map(|(_, value)| coerce::string_with_span(value, ctx.diagnostics))
+    {
+        Ok(Some(key)) => key,
+        Err(err) => return ctx.push_error(err),
+        Ok(None) => return,
+    };
+
+    if key.trim().is_empty() {
+        return ctx.push_attribute_validation_error("Discriminator key can't be empty.", true);
+    }
+
+    // The tag is implied by the class, so it can't also be a field.
+    if ast_class.iter_fields().any(|(_, f)| f.name() == key) {
+        return ctx.push_error(DatamodelError::new_validation_error(
+            &format!(
+                "`@@discriminator` key `{}` is already a field of class `{}`.",
+                key,
+                ast_class.name()
+            ),
+            span.clone(),
+        ));
+    }
+
+    attributes.discriminator = Some(key.to_string());
+}
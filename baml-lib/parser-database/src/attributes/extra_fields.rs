@@ -0,0 +1,55 @@
+use internal_baml_diagnostics::DatamodelError;
+use internal_baml_schema_ast::ast::{Class, FieldType, Identifier, WithName};
+
+use crate::{coerce, context::Context, types::ClassAttributes};
+
+pub(super) fn visit_extra_fields_attribute<'db>(
+    attributes: &mut ClassAttributes,
+    ast_class: &'db Class,
+    ctx: &mut Context<'db>,
+) {
+    let (name, span) = match ctx
+        .visit_default_arg_with_idx("field_name")
+        .map(|(_, value)| coerce::string_with_span(value, ctx.diagnostics))
+    {
+        Ok(Some(name)) => name,
+        Err(err) => return ctx.push_error(err),
+        Ok(None) => return,
+    };
+
+    let Some((field_id, field)) = ast_class.iter_fields().find(|(_, f)| f.name() == name) else {
+        return ctx.push_error(DatamodelError::new_validation_error(
+            &format!(
+                "Class `{}` has no field named `{}`.",
+                ast_class.name(),
+                name
+            ),
+            span.clone(),
+        ));
+    };
+
+    if !is_string_map(&field.field_type) {
+        return ctx.push_error(DatamodelError::new_validation_error(
+            &format!(
+                "`@@extra_fields` field `{}` is a `{}`, only map<string, string> is supported.",
+                name, field.field_type
+            ),
+            field.field_type.span().clone(),
+        ));
+    }
+
+    attributes.extra_fields = Some(field_id);
+}
+
+fn is_string_map(field_type: &FieldType) -> bool {
+    let is_string = |t: &FieldType| {
+        matches!(
+            t,
+            FieldType::Identifier(_, Identifier::Primitive(baml_types::TypeValue::String, _))
+        ) && !t.is_nullable()
+    };
+    match field_type {
+        FieldType::Dictionary(kv, _) => is_string(&kv.0) && is_string(&kv.1),
+        _ => false,
+    }
+}
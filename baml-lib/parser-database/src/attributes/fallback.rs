@@ -0,0 +1,27 @@
+use internal_baml_diagnostics::DatamodelError;
+use internal_baml_schema_ast::ast::{Enum, WithName};
+
+use crate::{coerce, context::Context, types::EnumAttributes};
+
+pub(super) fn visit_fallback_attribute<'db>(
+    attributes: &mut EnumAttributes,
+    ast_enum: &'db Enum,
+    ctx: &mut Context<'db>,
+) {
+    let (name, span) = match ctx
+        .visit_default_arg_with_idx("value")
+        .map(|(_, value)| coerce::string_with_span(value, ctx.diagnostics))
+    {
+        Ok(Some(name)) => name,
+        Err(err) => return ctx.push_error(err),
+        Ok(None) => return,
+    };
+
+    match ast_enum.iter_values().find(|(_, v)| v.name() == name) {
+        Some((value_id, _)) => attributes.fallback = Some(value_id),
+        None => ctx.push_error(DatamodelError::new_validation_error(
+            &format!("Enum `{}` has no value named `{}`.", ast_enum.name(), name),
+            span.clone(),
+        )),
+    }
+}
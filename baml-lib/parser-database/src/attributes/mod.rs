@@ -5,8 +5,12 @@ use internal_baml_schema_ast::ast::{
 
 mod alias;
 mod description;
+mod discriminator;
+mod extra_fields;
+mod fallback;
 mod get;
 mod meta;
+mod synonym;
 mod to_string_attribute;
 
 use crate::{
@@ -39,12 +43,30 @@ fn resolve_enum_attributes<'db>(enum_id: EnumId, ast_enum: &'db Enum, ctx: &mut
         if let Some(attrs) = to_string_attribute::visit(ctx, false) {
             enum_attributes.value_serilizers.insert(value_idx, attrs);
         }
+        let mut synonyms = Vec::new();
+        while ctx.visit_repeated_attr("synonym") {
+            synonym::visit_synonym_attribute(
+                &mut synonyms,
+                value_idx,
+                ast_enum,
+                &enum_attributes,
+                ctx,
+            );
+            ctx.validate_visited_arguments();
+        }
+        if !synonyms.is_empty() {
+            enum_attributes.value_synonyms.insert(value_idx, synonyms);
+        }
         ctx.validate_visited_attributes();
     }
 
     // Now validate the enum attributes.
     ctx.visit_attributes(enum_id.into());
     enum_attributes.serilizer = to_string_attribute::visit(ctx, true);
+    if ctx.visit_optional_single_attr("fallback") {
+        fallback::visit_fallback_attribute(&mut enum_attributes, ast_enum, ctx);
+        ctx.validate_visited_arguments();
+    }
     ctx.validate_visited_attributes();
 
     ctx.types.enum_attributes.insert(enum_id, enum_attributes);
@@ -64,6 +86,14 @@ fn resolve_class_attributes<'db>(class_id: ClassId, ast_class: &'db Class, ctx:
     // Now validate the class attributes.
     ctx.visit_attributes(class_id.into());
     class_attributes.serilizer = to_string_attribute::visit(ctx, true);
+    if ctx.visit_optional_single_attr("extra_fields") {
+        extra_fields::visit_extra_fields_attribute(&mut class_attributes, ast_class, ctx);
+        ctx.validate_visited_arguments();
+    }
+    if ctx.visit_optional_single_attr("discriminator") {
+        discriminator::visit_discriminator_attribute(&mut class_attributes, ast_class, ctx);
+        ctx.validate_visited_arguments();
+    }
     ctx.validate_visited_attributes();
 
     ctx.types
@@ -120,3 +150,177 @@ fn resolve_llm_variant_attributes<'db>(
         .variant_attributes
         .insert(variant_id, variant_attributes);
 }
+
+#[cfg(test)]
+mod test {
+    use crate::ParserDatabase;
+    use internal_baml_diagnostics::SourceFile;
+    use internal_baml_schema_ast::ast::WithName;
+
+    /// The validated database, or the messages of the errors found.
+    fn validate(schema: &str) -> Result<ParserDatabase, Vec<String>> {
+        let messages = |d: internal_baml_diagnostics::Diagnostics| {
+            d.errors()
+                .iter()
+                .map(|e| e.message().to_string())
+                .collect::<Vec<_>>()
+        };
+        let (ast, mut diagnostics) =
+            internal_baml_schema_ast::parse_schema(&SourceFile::from(schema)).map_err(messages)?;
+        let mut db = ParserDatabase::new();
+        db.add_ast(ast);
+        db.validate(&mut diagnostics).map_err(messages)?;
+        Ok(db)
+    }
+
+    fn validation_errors(schema: &str) -> Vec<String> {
+        match validate(schema) {
+            Ok(_) => panic!("Expected errors for:\n{}", schema),
+            Err(errors) => errors,
+        }
+    }
+
+    #[test]
+    fn extra_fields() {
+        let db = validate(
+            r#"
+            class Person {
+              name string
+              extras {string: string}
+              @@extra_fields("extras")
+            }
+            "#,
+        )
+        .unwrap();
+        let person = db.find_type_by_str("Person").unwrap().left().unwrap();
+        assert_eq!(person.extra_fields().unwrap().name(), "extras");
+
+        let errors = validation_errors(
+            r#"
+            class Person {
+              name string
+              @@extra_fields("extras")
+            }
+            "#,
+        );
+        assert_eq!(
+            errors,
+            ["Error validating: Class `Person` has no field named `extras`."]
+        );
+
+        let errors = validation_errors(
+            r#"
+            class Person {
+              name string
+              extras {string: int}
+              @@extra_fields("extras")
+            }
+            "#,
+        );
+        assert_eq!(
+            errors,
+            ["Error validating: `@@extra_fields` field `extras` is a `map<string, int>`, only map<string, string> is supported."]
+        );
+    }
+
+    #[test]
+    fn fallback() {
+        let db = validate(
+            r#"
+            enum Color {
+              Red
+              Other
+              @@fallback("Other")
+            }
+            "#,
+        )
+        .unwrap();
+        let color = db.find_type_by_str("Color").unwrap().right().unwrap();
+        assert_eq!(color.fallback().unwrap().name(), "Other");
+
+        let errors = validation_errors(
+            r#"
+            enum Color {
+              Red
+              @@fallback("Other")
+            }
+            "#,
+        );
+        assert_eq!(
+            errors,
+            ["Error validating: Enum `Color` has no value named `Other`."]
+        );
+    }
+
+    #[test]
+    fn synonym() {
+        let db = validate(
+            r#"
+            enum Color {
+              Red @synonym("crimson") @synonym("scarlet")
+              Blue
+            }
+            "#,
+        )
+        .unwrap();
+        let color = db.find_type_by_str("Color").unwrap().right().unwrap();
+        assert_eq!(
+            color.find_value("Red").unwrap().synonyms(),
+            ["crimson", "scarlet"]
+        );
+
+        let errors = validation_errors(
+            r#"
+            enum Color {
+              Red @synonym("crimson") @synonym("Crimson")
+              Blue
+            }
+            "#,
+        );
+        assert_eq!(
+            errors,
+            ["Error parsing attribute \"@synonym\": Duplicate synonym."]
+        );
+
+        let errors = validation_errors(
+            r#"
+            enum Color {
+              Red @synonym("blue")
+              Blue
+            }
+            "#,
+        );
+        assert_eq!(
+            errors,
+            ["Error validating: Synonym `blue` is ambiguous, it also refers to `Blue`."]
+        );
+    }
+
+    #[test]
+    fn discriminator() {
+        let db = validate(
+            r#"
+            class Cat {
+              name string
+              @@discriminator("type")
+            }
+            "#,
+        )
+        .unwrap();
+        let cat = db.find_type_by_str("Cat").unwrap().left().unwrap();
+        assert_eq!(cat.discriminator(), Some("type"));
+
+        let errors = validation_errors(
+            r#"
+            class Cat {
+              type string
+              @@discriminator("type")
+            }
+            "#,
+        );
+        assert_eq!(
+            errors,
+            ["Error validating: `@@discriminator` key `type` is already a field of class `Cat`."]
+        );
+    }
+}
@@ -0,0 +1,53 @@
+use internal_baml_diagnostics::DatamodelError;
+use internal_baml_schema_ast::ast::{Enum, EnumValueId, WithName};
+
+use crate::{coerce, context::Context, types::EnumAttributes};
+
+pub(super) fn visit_synonym_attribute<'db>(
+    synonyms: &mut Vec<String>,
+    value_id: EnumValueId,
+    ast_enum: &'db Enum,
+    attributes: &EnumAttributes,
+    ctx: &mut Context<'db>,
+) {
+    let (synonym, span) = match ctx
+        .visit_default_arg_with_idx("synonym")
+        .map(|(_, value)| coerce::string_with_span(value, ctx.diagnostics))
+    {
+        Ok(Some(synonym)) => synonym,
+        Err(err) => return ctx.push_error(err),
+        Ok(None) => return,
+    };
+
+    let synonym = synonym.trim();
+    if synonym.is_empty() {
+        return ctx.push_attribute_validation_error("Synonym can't be empty.", false);
+    }
+    if synonyms.iter().any(|s| s.eq_ignore_ascii_case(synonym)) {
+        return ctx.push_attribute_validation_error("Duplicate synonym.", false);
+    }
+
+    // Matching ignores case, so a synonym shared with another value could never be resolved.
+    let conflict = ast_enum
+        .iter_values()
+        .filter(|(id, _)| *id != value_id)
+        .find(|(id, v)| {
+            v.name().eq_ignore_ascii_case(synonym)
+                || attributes
+                    .value_synonyms
+                    .get(id)
+                    .is_some_and(|s| s.iter().any(|s| s.eq_ignore_ascii_case(synonym)))
+        });
+    if let Some((_, other)) = conflict {
+        return ctx.push_error(DatamodelError::new_validation_error(
+            &format!(
+                "Synonym `{}` is ambiguous, it also refers to `{}`.",
+                synonym,
+                other.name()
+            ),
+            span.clone(),
+        ));
+    }
+
+    synonyms.push(synonym.to_string());
+}
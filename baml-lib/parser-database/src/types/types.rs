@@ -9,6 +9,12 @@ pub struct EnumAttributes {
     pub value_serilizers: HashMap<EnumValueId, ToStringAttributes>,
 
     pub serilizer: Option<ToStringAttributes>,
+
+    /// The value to use when nothing else matches (`@@fallback`).
+    pub fallback: Option<EnumValueId>,
+
+    /// Extra strings that match a value, never rendered (`@synonym`).
+    pub value_synonyms: HashMap<EnumValueId, Vec<String>>,
 }
 
 #[derive(Debug, Default)]
@@ -16,6 +22,12 @@ pub struct ClassAttributes {
     pub field_serilizers: HashMap<FieldId, ToStringAttributes>,
 
     pub serilizer: Option<ToStringAttributes>,
+
+    /// The `map<string, string>` field that collects unknown keys (`@@extra_fields`).
+    pub extra_fields: Option<FieldId>,
+
+    /// The key that tags this class in a union with its name (`@@discriminator`).
+    pub discriminator: Option<String>,
 }
 
 #[derive(Debug, Default)]
@@ -58,6 +58,25 @@ impl<'db> ClassWalker<'db> {
             .into_iter()
     }
 
+    /// The field that collects unknown keys, set with `@@extra_fields(field_name)`.
+    pub fn extra_fields(self) -> Option<FieldWalker<'db>> {
+        self.db
+            .types
+            .class_attributes
+            .get(&self.id)
+            .and_then(|attrs| attrs.extra_fields)
+            .map(|field_id| self.walk((self.id, field_id, false)))
+    }
+
+    /// The key that tags this class with its name in unions, set with `@@discriminator("key")`.
+    pub fn discriminator(self) -> Option<&'db str> {
+        self.db
+            .types
+            .class_attributes
+            .get(&self.id)
+            .and_then(|attrs| attrs.discriminator.as_deref())
+    }
+
     /// Iterate all the scalar fields in a given class in the order they were defined.
     pub fn dependencies(self) -> &'db HashSet<String> {
         &self.db.types.class_dependencies[&self.id]
@@ -41,6 +41,16 @@ impl<'db> EnumWalker<'db> {
             .into_iter()
     }
 
+    /// The value used when nothing else matches, set with `@@fallback(VALUE)`.
+    pub fn fallback(self) -> Option<EnumValueWalker<'db>> {
+        self.db
+            .types
+            .enum_attributes
+            .get(&self.id)
+            .and_then(|attrs| attrs.fallback)
+            .map(|value_id| self.walk((self.id, value_id)))
+    }
+
     /// Find a value by name.
     pub fn find_value(&self, name: &str) -> Option<EnumValueWalker<'db>> {
         self.ast_enum()
@@ -141,6 +151,16 @@ impl<'db> EnumValueWalker<'db> {
     pub fn documentation(self) -> Option<&'db str> {
         self.r#enum().ast_enum()[self.id.1].documentation()
     }
+
+    /// Extra strings that match this value, set with `@synonym("...")`.
+    pub fn synonyms(self) -> &'db [String] {
+        self.db
+            .types
+            .enum_attributes
+            .get(&self.id.0)
+            .and_then(|attrs| attrs.value_synonyms.get(&self.id.1))
+            .map_or(&[], Vec::as_slice)
+    }
 }
 
 impl<'db> WithSpan for EnumValueWalker<'db> {